@@ -1,3 +1,11 @@
+use core::{
+    any::Any,
+    sync::atomic::{AtomicBool, AtomicU8, Ordering},
+};
+use std::panic;
+
+use serde_json::json;
+
 #[macro_export]
 macro_rules! color {
     (bright_blue, $s:expr) => {{
@@ -38,10 +46,169 @@ macro_rules! color {
     }};
 }
 
+/// Prints a status line to stderr starting with a colored status,
+/// or a JSON log record with `--format json`.
+#[macro_export]
+macro_rules! status {
+    ($color:ident, $status:expr) => {{
+        $crate::status!($color, $status, "")
+    }};
+    ($color:ident, $status:expr, $($arg:tt)+) => {{
+        $crate::log_at!($crate::coloring::Verbosity::Normal, $color, $status, $($arg)+)
+    }};
+}
+
+/// A warning, in red, which is still printed with `-q`.
+#[macro_export]
+macro_rules! warn {
+    ($status:expr, $($arg:tt)+) => {{
+        $crate::log_at!($crate::coloring::Verbosity::Quiet, bright_red, $status, $($arg)+)
+    }};
+}
+
+/// A status line only printed with `-v`.
+#[macro_export]
+macro_rules! verbose {
+    ($color:ident, $status:expr, $($arg:tt)+) => {{
+        $crate::log_at!($crate::coloring::Verbosity::Verbose, $color, $status, $($arg)+)
+    }};
+}
+
+/// A status line only printed with `-vv`.
+#[macro_export]
+macro_rules! debug {
+    ($color:ident, $status:expr, $($arg:tt)+) => {{
+        $crate::log_at!($crate::coloring::Verbosity::Debug, $color, $status, $($arg)+)
+    }};
+}
+
+#[doc(hidden)]
+#[macro_export]
+macro_rules! log_at {
+    ($level:expr, $color:ident, $status:expr, $($arg:tt)+) => {{
+        if $crate::coloring::shown($level, $crate::coloring::verbosity()) {
+            let message = format!($($arg)+);
+            if $crate::coloring::is_json() {
+                $crate::coloring::log_json($level, $status, &message);
+            } else if message.is_empty() {
+                eprintln!("{}", $crate::color!($color, $status));
+            } else {
+                eprintln!("{} {message}", $crate::color!($color, $status));
+            }
+        }
+    }};
+}
+
+/// How much is printed to stderr, set with `-q` and `-v`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Verbosity {
+    Quiet = 0,
+    Normal = 1,
+    Verbose = 2,
+    Debug = 3,
+}
+impl Verbosity {
+    pub const fn new(quiet: bool, verbose: usize) -> Self {
+        match (quiet, verbose) {
+            (true, _) => Self::Quiet,
+            (false, 0) => Self::Normal,
+            (false, 1) => Self::Verbose,
+            (false, _) => Self::Debug,
+        }
+    }
+}
+
+static JSON: AtomicBool = AtomicBool::new(false);
+static VERBOSITY: AtomicU8 = AtomicU8::new(Verbosity::Normal as u8);
+
+pub fn set_verbosity(verbosity: Verbosity) {
+    VERBOSITY.store(verbosity as u8, Ordering::Relaxed);
+}
+
+pub fn verbosity() -> Verbosity {
+    match VERBOSITY.load(Ordering::Relaxed) {
+        0 => Verbosity::Quiet,
+        1 => Verbosity::Normal,
+        2 => Verbosity::Verbose,
+        _ => Verbosity::Debug,
+    }
+}
+
+/// If a status line at `level` is printed with `verbosity`.
+pub fn shown(level: Verbosity, verbosity: Verbosity) -> bool {
+    verbosity >= level
+}
+
 pub fn set_override(color: bool) {
     owo_colors::set_override(color);
 }
 
+/// Switches status messages and panics to JSON log records, without colors.
+/// Only the first call does anything.
+pub fn set_json() {
+    if JSON.swap(true, Ordering::Relaxed) {
+        return;
+    }
+    owo_colors::set_override(false);
+    panic::set_hook(Box::new(|info| {
+        print_record("error", None, &panic_message(info.payload()));
+    }));
+}
+
+pub fn is_json() -> bool {
+    JSON.load(Ordering::Relaxed)
+}
+
+/// Prints a JSON log record to stderr, lines printed with `-q` are warnings and `-v` lines are debug.
+pub fn log_json(verbosity: Verbosity, status: &str, message: &str) {
+    let level = match verbosity {
+        Verbosity::Quiet => "warn",
+        Verbosity::Normal => "info",
+        Verbosity::Verbose | Verbosity::Debug => "debug",
+    };
+    print_record(level, Some(status), message);
+}
+
+fn print_record(level: &str, status: Option<&str>, message: &str) {
+    eprintln!(
+        "{}",
+        json!({
+            "level": level,
+            "status": status,
+            "message": strip(message),
+        })
+    );
+}
+
+/// The message of a panic, without colors.
+pub fn panic_message(payload: &(dyn Any + Send)) -> String {
+    let message = payload
+        .downcast_ref::<&str>()
+        .map(ToString::to_string)
+        .or_else(|| payload.downcast_ref::<String>().cloned())
+        .unwrap_or_default();
+    strip(&message)
+}
+
+/// Removes ANSI color codes, for messages that go somewhere other than the terminal.
+pub fn strip(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    let mut chars = s.chars();
+    while let Some(c) = chars.next() {
+        if c == '\x1b' {
+            // ESC [ ... final byte in @..=~
+            for c in chars.by_ref() {
+                if ('@'..='~').contains(&c) && c != '[' {
+                    break;
+                }
+            }
+        } else {
+            out.push(c);
+        }
+    }
+    out
+}
+
 #[cfg(test)]
 mod test {
     #[test]
@@ -56,4 +223,29 @@ mod test {
         _ = color!(green, "");
         _ = color!(magenta, "");
     }
+
+    #[test]
+    fn test_verbosity() {
+        use super::{shown, Verbosity};
+
+        assert_eq!(Verbosity::new(true, 2), Verbosity::Quiet);
+        assert_eq!(Verbosity::new(false, 0), Verbosity::Normal);
+        assert_eq!(Verbosity::new(false, 1), Verbosity::Verbose);
+        assert_eq!(Verbosity::new(false, 3), Verbosity::Debug);
+
+        assert!(shown(Verbosity::Quiet, Verbosity::Quiet));
+        assert!(!shown(Verbosity::Normal, Verbosity::Quiet));
+        assert!(shown(Verbosity::Normal, Verbosity::Normal));
+        assert!(!shown(Verbosity::Verbose, Verbosity::Normal));
+        assert!(shown(Verbosity::Verbose, Verbosity::Debug));
+    }
+
+    #[test]
+    fn test_strip() {
+        assert_eq!(
+            super::strip("\x1b[91mCould not\x1b[39m verify"),
+            "Could not verify"
+        );
+        assert_eq!(super::strip("plain"), "plain");
+    }
 }
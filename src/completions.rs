@@ -0,0 +1,131 @@
+use std::{
+    ffi::OsString,
+    path::{Path, PathBuf},
+};
+
+use directories::BaseDirs;
+
+use crate::data::InfoFileImm;
+
+/// Info keys that point to a completion file in the archive, and the XDG data dir they go in.
+static SHELLS: [(&str, &str); 3] = [
+    ("completion_bash", "bash-completion/completions"),
+    ("completion_zsh", "zsh/site-functions"),
+    ("completion_fish", "fish/vendor_completions.d"),
+];
+
+/// `$XDG_DATA_HOME` or `$HOME/.local/share`, since shells look there on every platform.
+fn data_dir() -> Option<PathBuf> {
+    data_dir_from(std::env::var_os("XDG_DATA_HOME"))
+}
+
+/// Relative values of `$XDG_DATA_HOME` are ignored, as the spec says.
+fn data_dir_from(xdg_data_home: Option<OsString>) -> Option<PathBuf> {
+    if let Some(dir) = xdg_data_home {
+        let dir = PathBuf::from(dir);
+        if dir.is_absolute() {
+            return Some(dir);
+        }
+    }
+
+    BaseDirs::new().map(|dirs| {
+        let mut dir = dirs.home_dir().to_path_buf();
+        dir.push(".local/share");
+        dir
+    })
+}
+
+fn install_path(dir: &str, archive_path: &str) -> Option<PathBuf> {
+    let file_name = Path::new(archive_path).file_name()?;
+
+    let mut path = data_dir()?;
+    path.push(dir);
+    path.push(file_name);
+    Some(path)
+}
+
+/// Checks if an archive entry is a completion declared in info.json.
+pub fn is_completion(info: &InfoFileImm, str_name: &str) -> bool {
+    SHELLS
+        .iter()
+        .any(|(key, _)| info.info.get(*key).is_some_and(|p| p.eq(str_name)))
+}
+
+/// Where a completion from the archive gets installed.
+pub fn path(info: &InfoFileImm, str_name: &str) -> Option<PathBuf> {
+    SHELLS
+        .iter()
+        .find(|(key, _)| info.info.get(*key).is_some_and(|p| p.eq(str_name)))
+        .and_then(|(_, dir)| install_path(dir, str_name))
+}
+
+/// Where all completions declared in info.json get installed.
+pub fn paths(info: &InfoFileImm) -> Vec<PathBuf> {
+    SHELLS
+        .iter()
+        .filter_map(|(key, dir)| install_path(dir, info.info.get(*key)?))
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use std::path::{Path, PathBuf};
+
+    use super::{data_dir, data_dir_from, is_completion, path, paths};
+    use crate::data::{InfoFile, InfoFileImm};
+
+    fn info() -> InfoFileImm {
+        let info: InfoFile = serde_json::from_str(include_str!("../test/info_3.json")).unwrap();
+        let mut info = InfoFileImm::convert(info, "TARGET");
+        info.info.insert(
+            "completion_bash".to_string(),
+            "comp/BINARY.bash".to_string(),
+        );
+        info.info
+            .insert("completion_fish".to_string(), "BINARY.fish".to_string());
+        info
+    }
+
+    #[test]
+    fn test_is_completion() {
+        let info = info();
+        assert!(is_completion(&info, "comp/BINARY.bash"));
+        assert!(is_completion(&info, "BINARY.fish"));
+        assert!(!is_completion(&info, "BINARY.bash"));
+        assert!(!is_completion(&info, "BINARY"));
+    }
+
+    #[test]
+    fn test_path() {
+        let info = info();
+        let Some(dir) = data_dir() else {
+            return;
+        };
+
+        assert_eq!(
+            path(&info, "comp/BINARY.bash"),
+            Some(dir.join("bash-completion/completions/BINARY.bash"))
+        );
+        assert_eq!(
+            path(&info, "BINARY.fish"),
+            Some(dir.join("fish/vendor_completions.d/BINARY.fish"))
+        );
+        assert_eq!(path(&info, "BINARY"), None);
+        assert_eq!(paths(&info).len(), 2);
+    }
+
+    #[test]
+    fn test_data_dir() {
+        assert_eq!(
+            data_dir_from(Some("/xdg".into())),
+            Some(PathBuf::from("/xdg"))
+        );
+
+        // Relative and unset both fall back to the home directory
+        let fallback = data_dir_from(None);
+        assert_eq!(data_dir_from(Some("relative".into())), fallback);
+        if let Some(fallback) = fallback {
+            assert!(fallback.ends_with(Path::new(".local/share")));
+        }
+    }
+}
@@ -1,44 +1,81 @@
 use crate::{
-    coloring,
-    data::{ConfigFile, ReportType},
-    APPLICATION, DEFAULT_INDEX, DEFAULT_INDEX_KEY, DEFAULT_TARGET, ORG, QUALIFIER,
+    coloring::{self, Verbosity},
+    data::{ConfigFile, EventVersion, HashPolicy, OutputFormat, PubKey, ReportType},
+    debug, status, target, verbose, warn, APPLICATION, DEFAULT_INDEX, DEFAULT_INDEX_KEY,
+    DEFAULT_TARGET, ORG, QUALIFIER,
 };
-use bpaf::Bpaf;
+use bpaf::{construct, env, short, Bpaf, Parser};
 use directories::ProjectDirs;
 use home::cargo_home;
 use indexmap::IndexSet;
-use std::{collections::HashSet, fs::File, io::Read, path::PathBuf};
+use std::{
+    collections::HashSet,
+    fs::{self, create_dir_all, File},
+    io::Read,
+    path::{Path, PathBuf},
+};
 
 static CONFIG_FILE: &str = "config.toml";
+/// Printed in place of secrets.
+static REDACTED: &str = "<redacted>";
+
+/// Sigstore bundles are verified instead of minisign signatures when this is set.
+#[derive(Clone, Debug)]
+#[cfg_attr(not(feature = "sigstore"), allow(dead_code))]
+pub struct SigstorePolicy {
+    pub identity: String,
+    pub issuer: String,
+    pub trust_root: PathBuf,
+}
 
 #[allow(clippy::struct_excessive_bools)]
 #[derive(Clone, Debug)]
 pub struct Config {
-    pub target: String,
+    pub targets: Vec<String>,
     pub safe: bool,
     pub update: bool,
     pub index: String,
-    pub pub_keys: HashSet<String>,
+    pub pub_keys: Vec<PubKey>,
+    pub sig_threshold: usize,
     pub auth: Option<String>,
+    pub max_download_size: Option<u64>,
     pub ci: bool,
     pub no_sig: bool,
     pub no_hash: bool,
     pub hash_bins: bool,
+    pub hash_policy: HashPolicy,
+    pub sigstore: Option<SigstorePolicy>,
     pub path: PathBuf,
+    pub prefix: PathBuf,
+    pub no_extras: bool,
+    pub completions: bool,
+    pub uninstall: bool,
     pub report_path: PathBuf,
     pub no_create_path: bool,
     pub reports: IndexSet<ReportType>,
     pub out: bool,
+    pub out_file: Option<PathBuf>,
+    pub out_socket: Option<PathBuf>,
+    pub event_version: EventVersion,
     pub get_latest: bool,
+    pub conf_file: Option<PathBuf>,
     pub packages: IndexSet<String>,
 }
+impl Config {
+    /// A copy that is safe to print, without the auth token.
+    pub fn redacted(&self) -> Self {
+        let mut config = self.clone();
+        config.auth = config.auth.map(|_| REDACTED.to_string());
+        config
+    }
+}
 
 #[allow(clippy::struct_excessive_bools)]
 #[derive(Clone, Debug, Bpaf)]
 #[bpaf(options("prebuilt"), generate(parse_args))]
 /// Download prebuilt binaires of some crates.io crates.
 struct Arguments {
-    /// Target of the binary to download.
+    /// Targets of the binary to download in a CSV format, the first one a crate was built for is used. auto detects the target of the host.
     #[bpaf(long("target"), env("PREBUILT_TARGET"), argument::<String>("TARGET"))]
     target: Option<String>,
     /// Do not overwrite binaries that already exist.
@@ -53,9 +90,18 @@ struct Arguments {
     /// A public verifying key encoded as base64. (Or a list of them using CSV). Must be used with --index.
     #[bpaf(long("pub-key"), env("PREBUILT_PUB_KEY"), argument::<String>("PUB_KEYS"), optional, parse(parse_pub_keys))]
     pub_key: HashSet<String>,
+    /// Public verifying keys with metadata, only from the config file.
+    #[bpaf(pure(Vec::new()))]
+    keys: Vec<PubKey>,
+    /// Number of distinct public keys that must sign each file. (Default: 1)
+    #[bpaf(long("sig-threshold"), env("PREBUILT_SIG_THRESHOLD"), argument::<usize>("COUNT"))]
+    sig_threshold: Option<usize>,
     /// Auth token to use for private indexes.
     #[bpaf(long("auth"), env("PREBUILT_AUTH"), argument::<String>("TOKEN"))]
     auth: Option<String>,
+    /// Max size in bytes of an archive to download. (Default: No limit)
+    #[bpaf(long("max-download-size"), env("PREBUILT_MAX_DOWNLOAD_SIZE"), argument::<u64>("BYTES"))]
+    max_download_size: Option<u64>,
     /// Index to use, pulling from config file. Overrides --index.
     #[bpaf(long("index-key"), env("PREBUILT_INDEX_KEY"), argument::<String>("INDEX_KEY"))]
     index_key: Option<String>,
@@ -71,9 +117,33 @@ struct Arguments {
     /// Hash and verify extracted binaries.
     #[bpaf(long("hash-bins"), env("PREBUILT_HASH_BINS"))]
     hash_bins: bool,
+    /// Which hashes to verify: strongest, all, or `minimum=HASH_TYPE`. (Default: strongest)
+    #[bpaf(long("hash-policy"), env("PREBUILT_HASH_POLICY"), argument::<String>("HASH_POLICY"), optional, parse(parse_hash_policy))]
+    hash_policy: Option<HashPolicy>,
+    /// Verify sigstore bundles signed by this identity (email or URI) instead of minisign signatures. Must be used with --index.
+    #[bpaf(long("sigstore-identity"), env("PREBUILT_SIGSTORE_IDENTITY"), argument::<String>("IDENTITY"))]
+    sigstore_identity: Option<String>,
+    /// OIDC issuer of the sigstore identity.
+    #[bpaf(long("sigstore-issuer"), env("PREBUILT_SIGSTORE_ISSUER"), argument::<String>("ISSUER"))]
+    sigstore_issuer: Option<String>,
+    /// Path to the sigstore trusted root (`trusted_root.json`) to verify bundles offline with.
+    #[bpaf(long("sigstore-trust-root"), env("PREBUILT_SIGSTORE_TRUST_ROOT"), argument::<PathBuf>("TRUST_ROOT"))]
+    sigstore_trust_root: Option<PathBuf>,
     /// Path to the folder where downloaded binaries will be installed. (Default: `$CARGO_HOME/bin`)
     #[bpaf(long("path"), env("PREBUILT_PATH"), argument::<PathBuf>("PATH"))]
     path: Option<PathBuf>,
+    /// Path to the folder where extra files from archives are installed under. (Default: Parent of --path)
+    #[bpaf(long("prefix"), env("PREBUILT_PREFIX"), argument::<PathBuf>("PREFIX"))]
+    prefix: Option<PathBuf>,
+    /// Do not install extra files from archives, like man pages.
+    #[bpaf(long("no-extras"), env("PREBUILT_NO_EXTRAS"))]
+    no_extras: bool,
+    /// Install shell completions provided by archives. (See `--docs/PATHS.md#completions`)
+    #[bpaf(long("completions"), env("PREBUILT_COMPLETIONS"))]
+    completions: bool,
+    /// Remove the binaries, extra files, and completions (with --completions) of packages instead of installing them. Files installed from extra directories are left behind.
+    #[bpaf(long("uninstall"), env("PREBUILT_UNINSTALL"))]
+    uninstall: bool,
     /// Path to the folder where the reports will be put (Default: See `--docs/PATHS.md#reports`)
     #[bpaf(long("report-path"), env("PREBUILT_REPORT_PATH"), argument::<PathBuf>("REPORT_PATH"))]
     report_path: Option<PathBuf>,
@@ -86,15 +156,35 @@ struct Arguments {
     /// Path to the config file (Default: See `--docs/PATHS.md#config`)
     #[bpaf(long("config"), env("PREBUILT_CONFIG"), argument::<PathBuf>("CONFIG_PATH"))]
     config: Option<PathBuf>,
+    /// Where the config file is or would be, set when loading it.
+    #[bpaf(pure(None))]
+    conf_file: Option<PathBuf>,
     /// Require a config file to be used. (--ci will override this)
     #[bpaf(short('r'), long("require-config"), env("PREBUILT_REQUIRE_CONFIG"))]
     require_config: bool,
     /// Output events.
     #[bpaf(long("out"), env("PREBUILT_OUT"))]
     out: bool,
+    /// Append events to a file instead of stdout, implies --out.
+    #[bpaf(long("out-file"), env("PREBUILT_OUT_FILE"), argument::<PathBuf>("OUT_FILE"))]
+    out_file: Option<PathBuf>,
+    /// Send events to a unix socket instead of stdout, implies --out.
+    #[bpaf(long("out-socket"), env("PREBUILT_OUT_SOCKET"), argument::<PathBuf>("OUT_SOCKET"))]
+    out_socket: Option<PathBuf>,
+    /// Format of events: 1 has a string as data, 2 has an object as data. (Default: 1) (See `--docs/EVENTS.md`)
+    #[bpaf(long("event-version"), env("PREBUILT_EVENT_VERSION"), argument::<String>("EVENT_VERSION"), optional, parse(parse_event_version))]
+    event_version: Option<EventVersion>,
+    /// Format of status messages on stderr: human or json. (Default: human)
+    #[bpaf(long("format"), env("PREBUILT_FORMAT"), argument::<String>("FORMAT"), optional, parse(parse_format))]
+    format: Option<OutputFormat>,
     /// Get latest versions of crates in index and then exit.
     #[bpaf(long("get-latest"), env("PREBUILT_GET_LATEST"))]
     get_latest: bool,
+    /// Only print warnings and errors to stderr.
+    #[bpaf(short('q'), long("quiet"), env("PREBUILT_QUIET"))]
+    quiet: bool,
+    #[bpaf(external(verbose))]
+    verbose: usize,
     /// Force color to be turned on.
     #[bpaf(long("color"), env("FORCE_COLOR"))]
     color: bool,
@@ -114,6 +204,36 @@ struct Arguments {
     packages: IndexSet<String>,
 }
 
+impl Arguments {
+    /// A copy that is safe to print, without the auth token.
+    fn redacted(&self) -> Self {
+        let mut args = self.clone();
+        args.auth = args.auth.map(|_| REDACTED.to_string());
+        args
+    }
+}
+
+/// -v flags, or `PREBUILT_VERBOSE` as a level like `verbose` in the config file.
+fn verbose() -> impl Parser<usize> {
+    let flags = short('v')
+        .long("verbose")
+        .help("Print more to stderr: -v for urls, hashes, config sources, and timing, -vv for everything. (PREBUILT_VERBOSE=0|1|2)")
+        .req_flag(())
+        .count();
+    let level = env("PREBUILT_VERBOSE")
+        .argument::<String>("LEVEL")
+        .parse(|s| match s.as_str() {
+            "" => Ok(0),
+            s => s
+                .parse::<usize>()
+                .map_err(|_| format!("PREBUILT_VERBOSE must be 0, 1, or 2, not {s}.")),
+        })
+        .hide()
+        .optional();
+    construct!(flags, level)
+        .map(|(flags, level)| if flags > 0 { flags } else { level.unwrap_or(0) })
+}
+
 #[allow(clippy::unnecessary_wraps)]
 fn parse_pub_keys(s: Option<String>) -> Result<HashSet<String>, String> {
     Ok(s.map_or_else(HashSet::new, |s| {
@@ -123,6 +243,10 @@ fn parse_pub_keys(s: Option<String>) -> Result<HashSet<String>, String> {
     }))
 }
 
+fn parse_hash_policy(s: Option<String>) -> Result<Option<HashPolicy>, String> {
+    s.map(HashPolicy::try_from).transpose()
+}
+
 fn parse_reports(s: Option<String>) -> Result<Option<IndexSet<ReportType>>, String> {
     match s {
         Some(s) => {
@@ -141,6 +265,18 @@ fn parse_reports(s: Option<String>) -> Result<Option<IndexSet<ReportType>>, Stri
     }
 }
 
+fn parse_event_version(s: Option<String>) -> Result<Option<EventVersion>, String> {
+    s.map(|s| {
+        EventVersion::try_from(s.as_str()).map_err(|()| format!("{s} is not an event version."))
+    })
+    .transpose()
+}
+
+fn parse_format(s: Option<String>) -> Result<Option<OutputFormat>, String> {
+    s.map(|s| OutputFormat::try_from(s.as_str()).map_err(|()| format!("{s} is not a format.")))
+        .transpose()
+}
+
 #[allow(clippy::needless_pass_by_value)]
 #[allow(clippy::unnecessary_wraps)]
 fn parse_packages(s: String) -> Result<IndexSet<String>, String> {
@@ -161,11 +297,17 @@ fn fill_from_file(args: &mut Arguments) {
         conf.push(CONFIG_FILE);
         conf
     } else {
-        eprintln!("Could not find default config directory! Config file will be ignored.");
+        warn!(
+            "Could not",
+            "find default config directory! Config file will be ignored."
+        );
         return;
     };
 
+    args.conf_file = Some(conf.clone());
+
     if conf.exists() {
+        verbose!(bright_white, "Config", "loading '{}'.", conf.display());
         let mut file = File::open(&conf).expect("Could not open config file.");
         let mut str = String::new();
         file.read_to_string(&mut str)
@@ -196,12 +338,32 @@ fn fill_from_file(args: &mut Arguments) {
                         };
                     }
 
-                    file_pull![target, index_key, path, report_path, reports];
+                    file_pull![
+                        target,
+                        index_key,
+                        hash_policy,
+                        event_version,
+                        out_file,
+                        out_socket,
+                        format,
+                        path,
+                        prefix,
+                        report_path,
+                        reports
+                    ];
+                    if args.verbose == 0 {
+                        if let Some(verbose) = prebuilt.verbose {
+                            args.verbose = verbose;
+                        }
+                    }
                     file_pull_switch![
+                        quiet,
                         safe,
                         no_sig,
                         no_hash,
                         hash_bins,
+                        no_extras,
+                        completions,
                         no_create_path,
                         out,
                         color,
@@ -214,14 +376,37 @@ fn fill_from_file(args: &mut Arguments) {
                         if let Some(cfi) = config.index {
                             for (_, i) in cfi {
                                 if i.index.eq(index) {
+                                    verbose!(
+                                        bright_white,
+                                        "Config",
+                                        "using settings for index {index}."
+                                    );
                                     if let Some(pk) = i.pub_key {
                                         for pk in pk {
                                             args.pub_key.insert(pk);
                                         }
                                     }
+                                    if let Some(keys) = i.keys {
+                                        args.keys.extend(keys);
+                                    }
                                     if args.auth.is_none() && i.auth.is_some() {
                                         args.auth = i.auth;
                                     }
+                                    if args.max_download_size.is_none() {
+                                        args.max_download_size = i.max_blob_size;
+                                    }
+                                    if args.sig_threshold.is_none() {
+                                        args.sig_threshold = i.sig_threshold;
+                                    }
+                                    if args.sigstore_identity.is_none() {
+                                        args.sigstore_identity = i.sigstore_identity;
+                                    }
+                                    if args.sigstore_issuer.is_none() {
+                                        args.sigstore_issuer = i.sigstore_issuer;
+                                    }
+                                    if args.sigstore_trust_root.is_none() {
+                                        args.sigstore_trust_root = i.sigstore_trust_root;
+                                    }
                                 }
                             }
                         }
@@ -230,15 +415,39 @@ fn fill_from_file(args: &mut Arguments) {
                         if let Some(cfi) = config.index {
                             for (key, i) in cfi {
                                 if key.eq(index_key) {
+                                    verbose!(
+                                        bright_white,
+                                        "Config",
+                                        "using index key {index_key} for index {}.",
+                                        i.index
+                                    );
                                     args.index = Some(i.index);
                                     if let Some(pk) = i.pub_key {
                                         for pk in pk {
                                             args.pub_key.insert(pk);
                                         }
                                     }
+                                    if let Some(keys) = i.keys {
+                                        args.keys.extend(keys);
+                                    }
                                     if args.auth.is_none() && i.auth.is_some() {
                                         args.auth = i.auth;
                                     }
+                                    if args.max_download_size.is_none() {
+                                        args.max_download_size = i.max_blob_size;
+                                    }
+                                    if args.sig_threshold.is_none() {
+                                        args.sig_threshold = i.sig_threshold;
+                                    }
+                                    if args.sigstore_identity.is_none() {
+                                        args.sigstore_identity = i.sigstore_identity;
+                                    }
+                                    if args.sigstore_issuer.is_none() {
+                                        args.sigstore_issuer = i.sigstore_issuer;
+                                    }
+                                    if args.sigstore_trust_root.is_none() {
+                                        args.sigstore_trust_root = i.sigstore_trust_root;
+                                    }
                                 }
                             }
                         }
@@ -249,7 +458,7 @@ fn fill_from_file(args: &mut Arguments) {
             Err(err) => panic!("Failed to parse config file.\n{err}"),
         }
     } else {
-        eprintln!("WARN: Could not find config, it will be ignored.");
+        warn!("Could not", "find config, it will be ignored.");
     }
 
     assert!(
@@ -263,17 +472,83 @@ fn fill_from_file(args: &mut Arguments) {
     );
 }
 
+/// Splits a CSV list of targets, in order of preference, detecting auto when `detect` is set.
+fn parse_targets(s: &str, detect: bool) -> Vec<String> {
+    let targets: Vec<String> = s
+        .split(',')
+        .map(str::trim)
+        .filter(|t| !t.is_empty())
+        .map(|t| {
+            if detect && t.eq("auto") {
+                target::detect()
+            } else {
+                t.to_string()
+            }
+        })
+        .collect();
+    assert!(
+        !targets.is_empty(),
+        "--target must have at least one target."
+    );
+    targets
+}
+
+fn sigstore_policy(
+    identity: Option<String>,
+    issuer: Option<String>,
+    trust_root: Option<PathBuf>,
+) -> Option<SigstorePolicy> {
+    match (identity, issuer, trust_root) {
+        (None, None, None) => None,
+        (Some(identity), Some(issuer), Some(trust_root)) if cfg!(feature = "sigstore") => {
+            Some(SigstorePolicy {
+                identity,
+                issuer,
+                trust_root,
+            })
+        }
+        (Some(_), Some(_), Some(_)) => {
+            panic!("Verifying sigstore bundles requires the sigstore feature!")
+        }
+        _ => panic!(
+            "--sigstore-identity, --sigstore-issuer, and --sigstore-trust-root must be used together."
+        ),
+    }
+}
+
+#[allow(clippy::too_many_lines)]
 fn convert(args: Arguments) -> Config {
-    let target = args.target.unwrap_or_else(|| DEFAULT_TARGET.to_owned());
+    // --get-latest never picks a target, so there is nothing to detect
+    let targets = parse_targets(
+        &args.target.unwrap_or_else(|| DEFAULT_TARGET.to_owned()),
+        !args.get_latest,
+    );
     let safe = args.safe;
     let update = args.update;
     let index = args.index.unwrap_or_else(|| DEFAULT_INDEX.to_string());
-    let pub_keys = args.pub_key;
+    let pub_keys = args
+        .pub_key
+        .into_iter()
+        .map(PubKey::from)
+        .chain(args.keys)
+        .collect();
+    let sig_threshold = args.sig_threshold.unwrap_or(1);
+    assert!(
+        sig_threshold > 0,
+        "--sig-threshold must be at least 1, use --no-sig to not verify signatures."
+    );
     let auth = args.auth;
+    let max_download_size = args.max_download_size;
     let ci = args.ci;
     let no_sig = args.no_sig;
     let no_hash = args.no_hash;
     let hash_bins = args.hash_bins;
+    let hash_policy = args.hash_policy.unwrap_or_default();
+    let sigstore = sigstore_policy(
+        args.sigstore_identity,
+        args.sigstore_issuer,
+        args.sigstore_trust_root,
+    );
 
     let path = args.path.unwrap_or_else(|| {
         let mut cargo_home = cargo_home().expect("Could not find cargo home directory. Please set $CARGO_HOME, or use $PREBUILT_PATH or --path");
@@ -283,6 +558,14 @@ fn convert(args: Arguments) -> Config {
         cargo_home
     });
 
+    let prefix = args.prefix.unwrap_or_else(|| {
+        path.parent()
+            .map_or_else(|| path.clone(), Path::to_path_buf)
+    });
+    let no_extras = args.no_extras;
+    let completions = args.completions;
+    let uninstall = args.uninstall;
+
     let report_path = args.report_path.unwrap_or_else(|| {
         ProjectDirs::from(QUALIFIER, ORG, APPLICATION).map_or_else(
             || panic!("Could not get report path, try setting $XDG_DATA_HOME or $HOME."),
@@ -300,7 +583,14 @@ fn convert(args: Arguments) -> Config {
         .reports
         .unwrap_or_else(|| IndexSet::from([ReportType::LicenseDL]));
 
-    let out = args.out;
+    let out_file = args.out_file;
+    let out_socket = args.out_socket;
+    assert!(
+        out_file.is_none() || out_socket.is_none(),
+        "--out-file and --out-socket cannot be used together."
+    );
+    let out = args.out || out_file.is_some() || out_socket.is_some();
+    let event_version = args.event_version.unwrap_or_default();
     let get_latest = args.get_latest;
 
     match (args.color, args.no_color) {
@@ -309,40 +599,173 @@ fn convert(args: Arguments) -> Config {
         _ => {}
     }
 
+    let conf_file = args.conf_file;
     let packages = args.packages;
 
     Config {
-        target,
+        targets,
         safe,
         update,
         index,
         pub_keys,
+        sig_threshold,
         auth,
+        max_download_size,
         ci,
         no_sig,
         no_hash,
         hash_bins,
+        hash_policy,
+        sigstore,
         path,
+        prefix,
+        no_extras,
+        completions,
+        uninstall,
         report_path,
         no_create_path,
         reports,
         out,
+        out_file,
+        out_socket,
+        event_version,
         get_latest,
+        conf_file,
         packages,
     }
 }
 
+/// Saves a public key for the index of the config into the config file, creating it if needed.
+/// The key is appended to the file, so comments and formatting in it are kept.
+pub fn pin_key(config: &Config, key: &PubKey) {
+    let Some(path) = &config.conf_file else {
+        warn!(
+            "Could not",
+            "find the config file, the key for index {} will not be saved.", config.index
+        );
+        return;
+    };
+
+    let existing = fs::read_to_string(path).unwrap_or_default();
+    let Some(toml) = append_key(&existing, &config.index, key) else {
+        warn!(
+            "Could not",
+            "add the key for index {} to '{}', add it to the keys of the index by hand.",
+            config.index,
+            path.display()
+        );
+        return;
+    };
+
+    if path
+        .parent()
+        .map_or(true, |dir| create_dir_all(dir).is_err())
+        || fs::write(path, toml).is_err()
+    {
+        warn!(
+            "Could not",
+            "save the key for index {} to '{}'.",
+            config.index,
+            path.display()
+        );
+    } else {
+        status!(
+            bright_purple,
+            "Saved",
+            "key for index {} to '{}'.",
+            config.index,
+            path.display()
+        );
+    }
+}
+
+/// Appends `key` as a `[[index.KEY.keys]]` table for `index`, adding an `[index.KEY]` table if
+/// the index is not in the config file yet. Returns None if the result would not be a valid
+/// config file with the key, like when the keys of the index are an inline array.
+fn append_key(existing: &str, index: &str, key: &PubKey) -> Option<String> {
+    let file: ConfigFile = basic_toml::from_str(existing).ok()?;
+    let indexes = file.index.unwrap_or_default();
+
+    let mut lines = Vec::new();
+    let index_key = if let Some((index_key, _)) = indexes.iter().find(|(_, i)| i.index.eq(index)) {
+        index_key.clone()
+    } else {
+        let name: String = index
+            .chars()
+            .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+            .collect();
+        let mut index_key = name.clone();
+        let mut n = 1;
+        while indexes.contains_key(&index_key) {
+            n += 1;
+            index_key = format!("{name}_{n}");
+        }
+        lines.push(String::new());
+        lines.push(format!("[index.{}]", toml_str(&index_key)));
+        lines.push(format!("index = {}", toml_str(index)));
+        index_key
+    };
+
+    lines.push(String::new());
+    lines.push(format!("[[index.{}.keys]]", toml_str(&index_key)));
+    if let Some(id) = &key.id {
+        lines.push(format!("id = {}", toml_str(id)));
+    }
+    lines.push(format!("key = {}", toml_str(&key.key)));
+
+    let mut toml = existing.to_string();
+    if !toml.is_empty() && !toml.ends_with('\n') {
+        toml.push('\n');
+    }
+    for line in lines {
+        toml.push_str(&line);
+        toml.push('\n');
+    }
+
+    let file: ConfigFile = basic_toml::from_str(&toml).ok()?;
+    file.index
+        .and_then(|mut indexes| indexes.remove(&index_key))
+        .and_then(|i| i.keys)
+        .is_some_and(|keys| keys.contains(key))
+        .then_some(toml)
+}
+
+/// A quoted toml string, json escapes are valid in toml basic strings.
+fn toml_str(s: &str) -> String {
+    serde_json::to_string(s).expect("Could not quote string.")
+}
+
+/// A config from arguments alone, without env vars or the config file.
+#[cfg(test)]
+pub fn from_args(args: &[&str]) -> Config {
+    convert(parse_args().run_inner(args).expect("Arguments are valid."))
+}
+
 pub fn get() -> Config {
     // arguments and env vars
     let mut args = parse_args().run();
-    #[cfg(debug_assertions)]
-    dbg!(&args);
+    // Early for --format, so loading the config file is logged in the right format
+    if args.format == Some(OutputFormat::Json) {
+        coloring::set_json();
+    }
+    coloring::set_verbosity(Verbosity::new(args.quiet, args.verbose));
+    debug!(bright_white, "Arguments", "{:#?}", args.redacted());
 
     // Load from config file
-    if !args.ci {
+    if args.ci {
+        verbose!(bright_white, "Config", "file skipped with --ci.");
+    } else {
         fill_from_file(&mut args);
-        #[cfg(debug_assertions)]
-        dbg!(&args);
+        if args.format == Some(OutputFormat::Json) {
+            coloring::set_json();
+        }
+        coloring::set_verbosity(Verbosity::new(args.quiet, args.verbose));
+        debug!(
+            bright_white,
+            "Arguments",
+            "with config file {:#?}",
+            args.redacted()
+        );
     }
 
     // Check 1
@@ -360,6 +783,9 @@ pub fn get() -> Config {
 mod test {
     use minisign_verify::{PublicKey, Signature};
 
+    use super::append_key;
+    use crate::data::{ConfigFile, PubKey};
+
     #[test]
     fn test_minisign1() {
         let data = include_bytes!("../test/pubdata.test");
@@ -370,4 +796,39 @@ mod test {
         let pk = PublicKey::from_base64(pubkey).unwrap();
         pk.verify(data, &signature, false).unwrap();
     }
+
+    #[test]
+    fn test_append_key() {
+        let key = PubKey {
+            id: Some("tofu".to_string()),
+            key: "KEY".to_string(),
+            valid_from: None,
+            valid_until: None,
+            revoked: false,
+        };
+        let keys = |toml: &str, index_key: &str| {
+            let file: ConfigFile = basic_toml::from_str(toml).unwrap();
+            file.index.unwrap()[index_key].keys.clone().unwrap()
+        };
+
+        // A new index gets its own table
+        let toml = append_key("", "https://example.com", &key).unwrap();
+        assert_eq!(keys(&toml, "https___example_com"), vec![key.clone()]);
+
+        // Comments and formatting are kept, and the existing table of the index is used
+        let existing = "# My indexes\n[index.mine]\nindex = \"https://example.com\" # comment\n";
+        let toml = append_key(existing, "https://example.com", &key).unwrap();
+        assert!(toml.starts_with(existing));
+        assert_eq!(keys(&toml, "mine"), vec![key.clone()]);
+
+        // Index keys are not reused for another index
+        let existing = "[index.https___example_com]\nindex = \"other\"\n";
+        let toml = append_key(existing, "https://example.com", &key).unwrap();
+        assert_eq!(keys(&toml, "https___example_com_2"), vec![key.clone()]);
+
+        // Keys of the index as an inline array cannot be appended to
+        let existing =
+            "[index.mine]\nindex = \"https://example.com\"\nkeys = [{ key = \"OTHER\" }]\n";
+        assert_eq!(append_key(existing, "https://example.com", &key), None);
+    }
 }
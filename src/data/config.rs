@@ -4,6 +4,8 @@ use std::{
 };
 
 use indexmap::IndexSet;
+
+use crate::data::{HashPolicy, PubKey};
 use serde::{Deserialize, Serialize};
 
 #[derive(Clone, Copy, Debug, Serialize, Deserialize, PartialEq, Eq, Hash)]
@@ -62,6 +64,56 @@ impl TryFrom<&str> for ReportType {
     }
 }
 
+/// Format of printed events, see EVENTS.md.
+#[derive(Clone, Copy, Debug, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub enum EventVersion {
+    #[default]
+    #[serde(rename = "1")]
+    V1,
+    #[serde(rename = "2")]
+    V2,
+}
+impl From<EventVersion> for &str {
+    fn from(value: EventVersion) -> Self {
+        match value {
+            EventVersion::V1 => "1",
+            EventVersion::V2 => "2",
+        }
+    }
+}
+impl TryFrom<&str> for EventVersion {
+    type Error = ();
+
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        match value {
+            "1" => Ok(Self::V1),
+            "2" => Ok(Self::V2),
+            _ => Err(()),
+        }
+    }
+}
+
+/// Format of status messages on stderr.
+#[derive(Clone, Copy, Debug, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub enum OutputFormat {
+    #[default]
+    #[serde(rename = "human")]
+    Human,
+    #[serde(rename = "json")]
+    Json,
+}
+impl TryFrom<&str> for OutputFormat {
+    type Error = ();
+
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        match value {
+            "human" => Ok(Self::Human),
+            "json" => Ok(Self::Json),
+            _ => Err(()),
+        }
+    }
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 #[serde(deny_unknown_fields)]
 pub struct ConfigFile {
@@ -78,11 +130,21 @@ pub struct ConfigFilePrebuilt {
     pub no_sig: Option<bool>,
     pub no_hash: Option<bool>,
     pub hash_bins: Option<bool>,
+    pub hash_policy: Option<HashPolicy>,
     pub path: Option<PathBuf>,
+    pub prefix: Option<PathBuf>,
+    pub no_extras: Option<bool>,
+    pub completions: Option<bool>,
     pub report_path: Option<PathBuf>,
     pub no_create_path: Option<bool>,
     pub reports: Option<IndexSet<ReportType>>,
     pub out: Option<bool>,
+    pub out_file: Option<PathBuf>,
+    pub out_socket: Option<PathBuf>,
+    pub format: Option<OutputFormat>,
+    pub quiet: Option<bool>,
+    pub verbose: Option<usize>,
+    pub event_version: Option<EventVersion>,
     pub color: Option<bool>,
     pub no_color: Option<bool>,
 }
@@ -92,7 +154,13 @@ pub struct ConfigFilePrebuilt {
 pub struct ConfigFileIndexes {
     pub index: String,
     pub pub_key: Option<HashSet<String>>,
+    pub sig_threshold: Option<usize>,
     pub auth: Option<String>, // TODO: Should be stored in base64?
+    pub max_blob_size: Option<u64>,
+    pub sigstore_identity: Option<String>,
+    pub sigstore_issuer: Option<String>,
+    pub sigstore_trust_root: Option<PathBuf>,
+    pub keys: Option<Vec<PubKey>>, // Tables go last for toml
 }
 
 #[cfg(test)]
@@ -116,4 +184,12 @@ mod test {
         let toml = "";
         let _: ConfigFile = basic_toml::from_str(toml).unwrap();
     }
+
+    #[test]
+    fn test_ser1() {
+        let toml = include_str!("../../test/config_1.toml");
+        let config: ConfigFile = basic_toml::from_str(toml).unwrap();
+        let toml = basic_toml::to_string(&config).unwrap();
+        let _: ConfigFile = basic_toml::from_str(&toml).unwrap();
+    }
 }
@@ -14,6 +14,33 @@ pub enum HashType {
     Sha3_256,
     #[serde(rename = "sha3_512")]
     Sha3_512,
+    #[serde(rename = "blake3")]
+    Blake3,
+}
+impl HashType {
+    /// Hash types from most to least preferred.
+    pub const PREFERRED: [Self; 5] = [
+        Self::Blake3,
+        Self::Sha3_512,
+        Self::Sha3_256,
+        Self::Sha512,
+        Self::Sha256,
+    ];
+
+    /// Digest size in bits, then the family (sha2 below sha3 and blake3).
+    const fn strength(self) -> (u16, u8) {
+        match self {
+            Self::Sha256 => (256, 0),
+            Self::Sha512 => (512, 0),
+            Self::Sha3_256 | Self::Blake3 => (256, 1),
+            Self::Sha3_512 => (512, 1),
+        }
+    }
+
+    /// Checks if this hash type is at least as strong as `other`, by digest size and then family.
+    pub fn at_least(self, other: Self) -> bool {
+        self.strength() >= other.strength()
+    }
 }
 impl From<HashType> for &str {
     fn from(value: HashType) -> Self {
@@ -22,6 +49,7 @@ impl From<HashType> for &str {
             HashType::Sha512 => "sha512",
             HashType::Sha3_256 => "sha3_256",
             HashType::Sha3_512 => "sha3_512",
+            HashType::Blake3 => "blake3",
         }
     }
 }
@@ -39,11 +67,58 @@ impl TryFrom<&str> for HashType {
             "sha512" => Ok(Self::Sha512),
             "sha3_256" => Ok(Self::Sha3_256),
             "sha3_512" => Ok(Self::Sha3_512),
+            "blake3" => Ok(Self::Blake3),
             _ => Err(()),
         }
     }
 }
 
+/// Which hashes have to be verified for a downloaded item.
+#[derive(Clone, Copy, Debug, Default, Serialize, Deserialize, PartialEq, Eq, Hash)]
+#[serde(try_from = "String", into = "String")]
+pub enum HashPolicy {
+    /// Only the most preferred hash.
+    #[default]
+    Strongest,
+    /// Every hash listed.
+    All,
+    /// The most preferred hash, which must be at least this hash type.
+    Minimum(HashType),
+}
+impl From<HashPolicy> for String {
+    fn from(value: HashPolicy) -> Self {
+        match value {
+            HashPolicy::Strongest => "strongest".to_string(),
+            HashPolicy::All => "all".to_string(),
+            HashPolicy::Minimum(t) => format!("minimum={}", Into::<&str>::into(t)),
+        }
+    }
+}
+impl TryFrom<&str> for HashPolicy {
+    type Error = ();
+
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        match value {
+            "strongest" => Ok(Self::Strongest),
+            "all" => Ok(Self::All),
+            _ => match value.split_once('=') {
+                Some(("minimum", t)) => Ok(Self::Minimum(t.try_into()?)),
+                _ => Err(()),
+            },
+        }
+    }
+}
+impl TryFrom<String> for HashPolicy {
+    type Error = String;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        value
+            .as_str()
+            .try_into()
+            .map_err(|()| format!("{value} is not a hash policy."))
+    }
+}
+
 #[derive(Debug, Deserialize)]
 #[serde(tag = "hashes_version")]
 pub enum HashesFile {
@@ -73,7 +148,7 @@ pub struct HashesFileBlobV1 {
 
 #[cfg(test)]
 mod test {
-    use super::HashesFile;
+    use super::{HashPolicy, HashType, HashesFile};
 
     #[test]
     fn test_deser1() {
@@ -86,4 +161,38 @@ mod test {
         let json = include_str!("../../test/hashes_2.json");
         let _: HashesFile = serde_json::from_str(json).unwrap();
     }
+
+    #[test]
+    fn test_deser3() {
+        let json = include_str!("../../test/hashes_3.json");
+        let _: HashesFile = serde_json::from_str(json).unwrap();
+    }
+
+    #[test]
+    fn test_hash_policy() {
+        assert_eq!(HashPolicy::try_from("all"), Ok(HashPolicy::All));
+        assert_eq!(
+            HashPolicy::try_from("minimum=sha512"),
+            Ok(HashPolicy::Minimum(HashType::Sha512))
+        );
+        assert_eq!(HashPolicy::try_from("minimum=md5"), Err(()));
+    }
+
+    #[test]
+    fn test_at_least() {
+        // A bigger digest is stronger, even when it is less preferred
+        assert!(!HashType::Sha3_256.at_least(HashType::Sha512));
+        assert!(!HashType::Blake3.at_least(HashType::Sha512));
+        assert!(HashType::Sha512.at_least(HashType::Sha3_256));
+        assert!(HashType::Sha3_512.at_least(HashType::Sha512));
+
+        // Same size, sha3 and blake3 over sha2
+        assert!(HashType::Sha3_256.at_least(HashType::Sha256));
+        assert!(!HashType::Sha256.at_least(HashType::Sha3_256));
+        assert!(HashType::Blake3.at_least(HashType::Sha3_256));
+
+        for t in HashType::PREFERRED {
+            assert!(t.at_least(t));
+        }
+    }
 }
@@ -15,17 +15,18 @@ pub struct InfoFileImm {
     #[allow(dead_code)]
     pub description: String, // Crate Description
     pub bins: Vec<String>, // Crate Binaries
-    #[allow(dead_code)]
+    pub extras: HashMap<String, String>, // Extra Files and Install Dirs
     pub info: HashMap<String, String>, // Metadata
     pub archive: InfoFileArchiveV2, // Archive Info
     pub files: InfoFileFilesV2, // File Names
+    pub target: String,  // Target Picked
     pub archive_name: String, // Archive Name
     pub archive_hashes: Hashes, // Archive Hashes
     pub bins_hashes: HashMap<String, Hashes>, // Binaries Hashes
     pub polyfill: Option<InfoFileImmPolyFill>, // Backwards Compat
 }
 impl InfoFileImm {
-    pub fn convert(info: InfoFile, target: &String) -> Self {
+    pub fn convert(info: InfoFile, target: &str) -> Self {
         match info {
             InfoFile::V1(info) => Self {
                 id: info.id,
@@ -34,6 +35,7 @@ impl InfoFileImm {
                 git: info.git,
                 description: info.description,
                 bins: info.bins,
+                extras: HashMap::new(),
                 info: info.info,
                 archive: InfoFileArchiveV2 {
                     compression: info.archive.compression,
@@ -44,6 +46,7 @@ impl InfoFileImm {
                     deps: info.files.deps,
                     audit: info.files.audit,
                 },
+                target: target.to_string(),
                 archive_name: format!("{target}.{}", info.archive.ext),
                 archive_hashes: HashMap::new(),
                 bins_hashes: HashMap::new(),
@@ -69,14 +72,12 @@ impl InfoFileImm {
                     git: info.git,
                     description: info.description,
                     bins: info.bins,
+                    extras: info.extras.unwrap_or_default(),
                     info: info.info,
                     archive: info.archive,
                     files: info.files,
-                    archive_name: info // TODO: Fail on target not found, here?
-                        .targets
-                        .get(target)
-                        .cloned()
-                        .unwrap_or_else(|| format!("{target}.tar.gz")),
+                    target: target.to_string(),
+                    archive_name: info.targets.get(target).cloned().unwrap_or_default(),
                     archive_hashes: hashes.archive,
                     bins_hashes: hashes.bins,
                     polyfill: None,
@@ -86,6 +87,11 @@ impl InfoFileImm {
     }
 }
 
+/// The first of `targets` that the crate was built for, if any.
+pub fn pick_target<'a>(targets: &'a [String], built_for: &[&str]) -> Option<&'a String> {
+    targets.iter().find(|t| built_for.contains(&t.as_str()))
+}
+
 #[derive(Debug)]
 pub struct InfoFileImmPolyFill {
     pub hash_file: String,
@@ -100,6 +106,17 @@ pub enum InfoFile {
     #[serde(rename = "2")]
     V2(InfoFileV2),
 }
+impl InfoFile {
+    /// Targets the crate was built for, sorted.
+    pub fn built_for(&self) -> Vec<&str> {
+        let mut built_for: Vec<&str> = match self {
+            Self::V1(info) => info.targets.iter().map(String::as_str).collect(),
+            Self::V2(info) => info.targets.keys().map(String::as_str).collect(),
+        };
+        built_for.sort_unstable();
+        built_for
+    }
+}
 
 //region Info File V2
 #[derive(Debug, Deserialize)]
@@ -111,6 +128,7 @@ pub struct InfoFileV2 {
     pub git: String,                               // Url to Git
     pub description: String,                       // Crate Description
     pub bins: Vec<String>,                         // Crate Binaries
+    pub extras: Option<HashMap<String, String>>,   // Extra Files and Install Dirs
     pub info: HashMap<String, String>,             // Metadata
     pub archive: InfoFileArchiveV2,                // Archive Info
     pub files: InfoFileFilesV2,                    // File Names
@@ -122,8 +140,7 @@ pub struct InfoFileV2 {
 #[serde(deny_unknown_fields)]
 pub struct InfoFileArchiveV2 {
     pub compression: String, // Archive Compression Type
-    #[allow(dead_code)]
-    pub package: String, // Archive Packing Type
+    pub package: String,     // Archive Packing Type
 }
 
 #[derive(Debug, Deserialize)]
@@ -148,8 +165,7 @@ pub struct InfoFileV1 {
     pub info: HashMap<String, String>, // Metadata
     pub archive: InfoFileArchiveV1,    // Archive Info
     pub files: InfoFileFilesV1,        // File Names
-    #[allow(dead_code)]
-    pub targets: Vec<String>, // Targets Built For
+    pub targets: Vec<String>,          // Targets Built For
 }
 
 #[derive(Debug, Deserialize)]
@@ -174,7 +190,7 @@ pub struct InfoFileFilesV1 {
 
 #[cfg(test)]
 mod test {
-    use super::InfoFile;
+    use super::{pick_target, InfoFile, InfoFileImm};
 
     #[test]
     fn test_deser1() {
@@ -187,4 +203,31 @@ mod test {
         let json = include_str!("../../test/info_2.json");
         let _: InfoFile = serde_json::from_str(json).unwrap();
     }
+
+    #[test]
+    fn test_deser3() {
+        let json = include_str!("../../test/info_3.json");
+        let _: InfoFile = serde_json::from_str(json).unwrap();
+    }
+
+    #[test]
+    fn test_pick_target() {
+        let targets = ["MISSING".to_string(), "TARGET".to_string()];
+
+        let json = include_str!("../../test/info_3.json");
+        let info: InfoFile = serde_json::from_str(json).unwrap();
+        let target = pick_target(&targets, &info.built_for()).unwrap().clone();
+        let info = InfoFileImm::convert(info, &target);
+        assert_eq!(info.target, "TARGET");
+        assert_eq!(info.archive_name, "FILE NAME");
+        assert!(!info.archive_hashes.is_empty());
+
+        let json = include_str!("../../test/info_1.json");
+        let info: InfoFile = serde_json::from_str(json).unwrap();
+        assert_eq!(pick_target(&targets, &info.built_for()), Some(&targets[1]));
+        assert_eq!(
+            pick_target(&["TARGTE".to_string()], &info.built_for()),
+            None
+        );
+    }
 }
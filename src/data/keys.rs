@@ -0,0 +1,108 @@
+use serde::{Deserialize, Serialize};
+
+/// A public minisign verifying key, with optional metadata so it can be rotated or revoked.
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(deny_unknown_fields)]
+pub struct PubKey {
+    pub id: Option<String>,
+    pub key: String,
+    pub valid_from: Option<String>,  // YYYY-MM-DD
+    pub valid_until: Option<String>, // YYYY-MM-DD
+    #[serde(default)]
+    pub revoked: bool,
+}
+impl PubKey {
+    /// Checks if the key can be used on `today` (YYYY-MM-DD).
+    /// Malformed dates make the key unusable instead of ignoring them.
+    pub fn is_valid(&self, today: &str) -> bool {
+        !self.revoked
+            && self
+                .valid_from
+                .as_ref()
+                .map_or(true, |from| is_date(from) && from.as_str() <= today)
+            && self
+                .valid_until
+                .as_ref()
+                .map_or(true, |until| is_date(until) && until.as_str() >= today)
+    }
+}
+impl From<String> for PubKey {
+    fn from(value: String) -> Self {
+        Self {
+            id: None,
+            key: value,
+            valid_from: None,
+            valid_until: None,
+            revoked: false,
+        }
+    }
+}
+
+/// Keys that can be used, a key revoked anywhere cannot be used even if it is listed again.
+pub fn usable_keys<'a>(keys: &'a [PubKey], today: &str) -> Vec<&'a PubKey> {
+    keys.iter()
+        .filter(|k| k.is_valid(today))
+        .filter(|k| !keys.iter().any(|r| r.revoked && r.key.eq(&k.key)))
+        .collect()
+}
+
+/// Only YYYY-MM-DD is allowed, so dates compare correctly as strings.
+fn is_date(s: &str) -> bool {
+    s.len() == 10
+        && s.char_indices().all(|(i, c)| match i {
+            4 | 7 => c == '-',
+            _ => c.is_ascii_digit(),
+        })
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(tag = "keys_version")]
+pub enum KeysFile {
+    #[serde(rename = "1")]
+    V1(KeysFileV1),
+}
+
+/// Key rotation file published by an index.
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct KeysFileV1 {
+    pub keys: Vec<PubKey>,
+}
+impl From<KeysFile> for KeysFileV1 {
+    fn from(value: KeysFile) -> Self {
+        match value {
+            KeysFile::V1(f) => f,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{usable_keys, KeysFile, KeysFileV1, PubKey};
+
+    #[test]
+    fn test_deser1() {
+        let json = include_str!("../../test/keys_1.json");
+        let _: KeysFile = serde_json::from_str(json).unwrap();
+    }
+
+    #[test]
+    fn test_usable_keys() {
+        let json = include_str!("../../test/keys_1.json");
+        let keys: KeysFile = serde_json::from_str(json).unwrap();
+        let mut keys = KeysFileV1::from(keys).keys;
+        keys.push(PubKey::from("RWRevoked".to_string()));
+
+        let usable: Vec<Option<&str>> = usable_keys(&keys, "2026-06-01")
+            .into_iter()
+            .map(|k| k.id.as_deref())
+            .collect();
+        assert_eq!(usable, vec![Some("2026")]);
+
+        let usable: Vec<Option<&str>> = usable_keys(&keys, "2025-12-31")
+            .into_iter()
+            .map(|k| k.id.as_deref())
+            .collect();
+        assert_eq!(usable, vec![Some("2025")]);
+    }
+}
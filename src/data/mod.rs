@@ -1,10 +1,12 @@
 mod config;
 mod hashes;
 mod info;
+mod keys;
 
 pub use config::*;
 pub use hashes::*;
 pub use info::*;
+pub use keys::*;
 
 use crate::config::Config;
 
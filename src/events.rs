@@ -1,96 +1,385 @@
-use serde_json::json;
+use std::{
+    fs::OpenOptions,
+    io::{self, Write},
+    panic,
+    path::Path,
+    sync::Mutex,
+};
 
-use crate::data::Meta;
+use serde_json::{json, Value};
 
-static EVENT_VERSION: &str = "1";
+use crate::{
+    color, coloring,
+    config::Config,
+    data::{EventVersion, HashType, InfoFileImm, Meta},
+    warn,
+};
 
-fn event(id: &str, version: &str, event: &str, data: &str) {
-    println!(
-        "{}",
-        serde_json::to_string(&json!({
+/// The crate and version being worked on, for error events.
+static CURRENT: Mutex<Option<(String, String)>> = Mutex::new(None);
+/// Where events are written to as NDJSON, stdout if not set.
+static SINK: Mutex<Option<Box<dyn Write + Send>>> = Mutex::new(None);
+
+/// Sends events to --out-file or --out-socket, if either is set.
+pub fn set_sink(config: &Config) {
+    let sink = if let Some(path) = &config.out_file {
+        open_file(path)
+    } else if let Some(path) = &config.out_socket {
+        open_socket(path)
+    } else {
+        return;
+    };
+
+    if let Ok(mut s) = SINK.lock() {
+        *s = Some(sink);
+    }
+}
+
+fn open_file(path: &Path) -> Box<dyn Write + Send> {
+    let file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .unwrap_or_else(|_| {
+            panic!(
+                "{} open event file '{}'.",
+                color!(bright_red, "Could not"),
+                path.display()
+            )
+        });
+    Box::new(file)
+}
+
+#[cfg(unix)]
+fn open_socket(path: &Path) -> Box<dyn Write + Send> {
+    let socket = std::os::unix::net::UnixStream::connect(path).unwrap_or_else(|_| {
+        panic!(
+            "{} connect to event socket '{}'.",
+            color!(bright_red, "Could not"),
+            path.display()
+        )
+    });
+    Box::new(socket)
+}
+
+#[cfg(not(unix))]
+fn open_socket(_path: &Path) -> Box<dyn Write + Send> {
+    panic!("--out-socket is only supported on unix.");
+}
+
+fn write_line(line: &str) {
+    let mut sink = SINK.lock().ok();
+    match sink.as_mut().and_then(|s| s.as_mut()) {
+        Some(sink) => {
+            if writeln!(sink, "{line}")
+                .and_then(|()| sink.flush())
+                .is_err()
+            {
+                warn!("Could not", "write event.");
+            }
+        }
+        None => {
+            let _ = writeln!(io::stdout(), "{line}");
+        }
+    }
+}
+
+/// Prints an event, v1 events only have the `v1` string as data while v2 events have the `v2` object.
+fn event(event_version: EventVersion, id: &str, version: &str, event: &str, v1: &str, v2: Value) {
+    let data = match event_version {
+        EventVersion::V1 => Value::from(v1),
+        EventVersion::V2 => v2,
+    };
+
+    write_line(
+        &serde_json::to_string(&json!({
             "crate": id,
             "version": version,
-            "event_version": EVENT_VERSION,
+            "event_version": Into::<&str>::into(event_version),
             "event": event,
             "data": data,
         }))
-        .unwrap_or_else(|_| "Could not generate {event} event.".to_string())
+        .unwrap_or_else(|_| "Could not generate {event} event.".to_string()),
+    );
+}
+
+fn meta_event(meta: &Meta, name: &str, v1: &str, v2: Value) {
+    event(
+        meta.config.event_version,
+        meta.id,
+        meta.version,
+        name,
+        v1,
+        v2,
     );
 }
 
 pub fn info_verify(meta: &Meta, verified: bool) {
     if meta.config.out {
-        event(
-            meta.id,
-            meta.version,
+        meta_event(
+            meta,
             "info_verified",
             &verified.to_string(),
+            json!({ "verified": verified }),
         );
     }
 }
 
 pub fn hashes_verify(meta: &Meta, verified: bool) {
     if meta.config.out {
-        event(
-            meta.id,
-            meta.version,
+        meta_event(
+            meta,
             "hashes_verified",
             &verified.to_string(),
+            json!({ "verified": verified }),
         );
     }
 }
 
-pub fn target(meta: &Meta) {
-    if meta.config.out {
-        event(meta.id, meta.version, "target", &meta.config.target);
+/// v1 sends the target from the config before info.json is fetched, as it always has.
+pub fn configured_target(meta: &Meta) {
+    if meta.config.out && meta.config.event_version == EventVersion::V1 {
+        let targets = meta.config.targets.join(",");
+        meta_event(meta, "target", &targets, Value::Null);
+    }
+}
+
+/// v2 sends the target picked for the crate, once info.json is fetched.
+pub fn target(meta: &Meta, target: &str) {
+    if meta.config.out && meta.config.event_version != EventVersion::V1 {
+        meta_event(meta, "target", target, json!({ "target": target }));
     }
 }
 
 pub fn binary_installed(meta: &Meta, path: &str) {
     if meta.config.out {
-        event(meta.id, meta.version, "bin_installed", path);
+        meta_event(meta, "bin_installed", path, json!({ "path": path }));
     }
 }
 
-pub fn installed(meta: &Meta) {
+pub fn extra_installed(meta: &Meta, path: &str) {
     if meta.config.out {
-        event(
-            meta.id,
-            meta.version,
+        meta_event(meta, "extra_installed", path, json!({ "path": path }));
+    }
+}
+
+pub fn completion_installed(meta: &Meta, path: &str) {
+    if meta.config.out {
+        meta_event(meta, "completion_installed", path, json!({ "path": path }));
+    }
+}
+
+pub fn removed(meta: &Meta, path: &str) {
+    if meta.config.out {
+        meta_event(meta, "removed", path, json!({ "path": path }));
+    }
+}
+
+pub fn uninstalled(meta: &Meta, info: &InfoFileImm) {
+    if meta.config.out {
+        meta_event(
+            meta,
+            "uninstalled",
+            &format!("{}@{}", meta.id, meta.version),
+            json!({ "bins": info.bins }),
+        );
+    }
+}
+
+pub fn installed(meta: &Meta, info: &InfoFileImm) {
+    if meta.config.out {
+        meta_event(
+            meta,
             "installed",
             &format!("{}@{}", meta.id, meta.version),
+            json!({ "target": info.target, "bins": info.bins }),
         );
     }
 }
 
 pub fn no_update(meta: &Meta) {
     if meta.config.out {
-        event(meta.id, meta.version, "no_update", "skip");
+        meta_event(meta, "no_update", "skip", json!({ "reason": "up_to_date" }));
     }
 }
 
-pub fn wrote_report(meta: &Meta, report_type: &str) {
+pub fn wrote_report(meta: &Meta, report_type: &str, path: &str) {
     if meta.config.out {
-        event(meta.id, meta.version, "wrote_report", report_type);
+        meta_event(
+            meta,
+            "wrote_report",
+            report_type,
+            json!({ "report": report_type, "path": path }),
+        );
     }
 }
 
 pub fn print_license(meta: &Meta, text: &str) {
-    event(meta.id, meta.version, "print_license", text);
+    meta_event(
+        meta,
+        "print_license",
+        text,
+        json!({ "report": "license", "text": text }),
+    );
 }
 
+/// info.json is embedded as an object in v2, instead of as a string.
 pub fn print_info_json(meta: &Meta, text: &str) {
-    event(meta.id, meta.version, "print_info_json", text);
+    let info = serde_json::from_str::<Value>(text).unwrap_or_else(|_| Value::from(text));
+    meta_event(
+        meta,
+        "print_info_json",
+        text,
+        json!({ "report": "info_json", "info": info }),
+    );
 }
 
 pub fn print_deps(meta: &Meta, text: &str) {
-    event(meta.id, meta.version, "print_deps", text);
+    meta_event(
+        meta,
+        "print_deps",
+        text,
+        json!({ "report": "deps", "text": text }),
+    );
 }
 
 pub fn print_audit(meta: &Meta, text: &str) {
-    event(meta.id, meta.version, "print_audit", text);
+    meta_event(
+        meta,
+        "print_audit",
+        text,
+        json!({ "report": "audit", "text": text }),
+    );
+}
+
+pub fn get_latest(event_version: EventVersion, id: &str, version: &str) {
+    event(
+        event_version,
+        id,
+        version,
+        "latest_version",
+        version,
+        json!({ "latest": version }),
+    );
+}
+
+/// Marks which crate and version errors are for.
+pub fn current(id: &str, version: &str) {
+    if let Ok(mut current) = CURRENT.lock() {
+        *current = Some((id.to_string(), version.to_string()));
+    }
+}
+
+/// Prints an error event for every panic, before the panic message.
+pub fn error_hook(event_version: EventVersion) {
+    let default_hook = panic::take_hook();
+    panic::set_hook(Box::new(move |info| {
+        let message = coloring::panic_message(info.payload());
+
+        let (id, version) = CURRENT
+            .lock()
+            .ok()
+            .and_then(|c| c.clone())
+            .unwrap_or_default();
+        event(
+            event_version,
+            &id,
+            &version,
+            "error",
+            &message,
+            json!({ "message": message }),
+        );
+
+        default_hook(info);
+    }));
+}
+
+pub fn download_started(meta: &Meta, file: &str) {
+    if meta.config.out {
+        meta_event(meta, "download_started", file, json!({ "file": file }));
+    }
+}
+
+pub fn download_progress(meta: &Meta, file: &str, bytes: u64) {
+    if meta.config.out {
+        meta_event(
+            meta,
+            "download_progress",
+            &bytes.to_string(),
+            json!({ "file": file, "bytes": bytes }),
+        );
+    }
 }
 
-pub fn get_latest(id: &str, version: &str) {
-    event(id, version, "latest_version", version);
+pub fn download_finished(meta: &Meta, file: &str, bytes: u64) {
+    if meta.config.out {
+        meta_event(
+            meta,
+            "download_finished",
+            &bytes.to_string(),
+            json!({ "file": file, "bytes": bytes }),
+        );
+    }
+}
+
+fn hash_types(hashes: &[(HashType, String)]) -> Vec<&'static str> {
+    hashes.iter().map(|(t, _)| Into::<&str>::into(t)).collect()
+}
+
+pub fn archive_verified(meta: &Meta, file: &str, hashes: &[(HashType, String)]) {
+    if meta.config.out {
+        meta_event(
+            meta,
+            "archive_verified",
+            file,
+            json!({ "file": file, "hashes": hash_types(hashes) }),
+        );
+    }
+}
+
+pub fn bin_verified(meta: &Meta, bin: &str, hashes: &[(HashType, String)]) {
+    if meta.config.out {
+        meta_event(
+            meta,
+            "bin_verified",
+            bin,
+            json!({ "bin": bin, "hashes": hash_types(hashes) }),
+        );
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::io::{BufRead, BufReader};
+
+    use super::{get_latest, open_file, SINK};
+    use crate::data::EventVersion;
+
+    #[cfg(unix)]
+    #[test]
+    fn test_sinks() {
+        use super::open_socket;
+        use std::os::unix::net::UnixListener;
+
+        let dir = tempfile::tempdir().unwrap();
+
+        let path = dir.path().join("events.sock");
+        let listener = UnixListener::bind(&path).unwrap();
+        *SINK.lock().unwrap() = Some(open_socket(&path));
+        get_latest(EventVersion::V2, "crate", "1.0.0");
+        let (stream, _) = listener.accept().unwrap();
+        let mut line = String::new();
+        BufReader::new(stream).read_line(&mut line).unwrap();
+        let event: serde_json::Value = serde_json::from_str(&line).unwrap();
+        assert_eq!(event["event"], "latest_version");
+        assert_eq!(event["data"]["latest"], "1.0.0");
+
+        let path = dir.path().join("events.ndjson");
+        *SINK.lock().unwrap() = Some(open_file(&path));
+        get_latest(EventVersion::V1, "crate", "1.0.0");
+        get_latest(EventVersion::V1, "crate", "1.0.1");
+        *SINK.lock().unwrap() = None;
+        let lines = std::fs::read_to_string(&path).unwrap();
+        assert_eq!(lines.lines().count(), 2);
+    }
 }
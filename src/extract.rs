@@ -0,0 +1,741 @@
+use std::{
+    collections::HashSet,
+    fs::{create_dir_all, File},
+    io::{self, BufReader, Read},
+    path::{Component, Path, PathBuf},
+};
+
+use flate2::read::GzDecoder;
+use tar::Archive;
+use tempfile::NamedTempFile;
+use zip::ZipArchive;
+
+use crate::{
+    color, completions,
+    data::{InfoFileArchiveV2, InfoFileImm, Meta},
+    events,
+    get::Fetcher,
+    hashing::HashWriter,
+    status, warn,
+};
+
+const EXTRACT_ENTRY_LIMIT: u64 = 1_048_576 * 250; // 250 MB
+const EXTRACT_TOTAL_LIMIT: u64 = 1_048_576 * 500; // 500 MB
+
+/// Checks if the packaging and compression of an archive can be extracted.
+/// Zip archives compress per entry, so their compression is ignored.
+pub fn check_supported(meta: &Meta, archive: &InfoFileArchiveV2) {
+    let feature = match (archive.package.as_str(), archive.compression.as_str()) {
+        ("tar", "gz") | ("zip", _) => return,
+        ("tar", "xz") if cfg!(feature = "archive-xz") => return,
+        ("tar", "zst") if cfg!(feature = "archive-zstd") => return,
+        ("tar", "bz2") if cfg!(feature = "archive-bz2") => return,
+        ("tar", "xz") => "archive-xz",
+        ("tar", "zst") => "archive-zstd",
+        ("tar", "bz2") => "archive-bz2",
+        (package, compression) => panic!(
+            "{}@{} uses {} archive packaging {package} with compression {compression}",
+            meta.id,
+            meta.version,
+            color!(bright_red, "unsupported"),
+        ),
+    };
+
+    panic!(
+        "{}@{} uses {} compressed archives, which requires the {feature} feature!",
+        meta.id, meta.version, archive.compression
+    );
+}
+
+pub fn extract(meta: &Meta, info: &InfoFileImm, archive: File) {
+    status!(bright_blue, "Extracting", "{}@{}...", meta.id, meta.version);
+
+    Installer::new(meta, info).extract(archive);
+}
+
+fn extract_tar(installer: &mut Installer, archive: File) {
+    let archive = BufReader::new(archive);
+    let decoder: Box<dyn Read> = match installer.info.archive.compression.as_str() {
+        "gz" => Box::new(GzDecoder::new(archive)),
+        #[cfg(feature = "archive-xz")]
+        "xz" => Box::new(xz2::read::XzDecoder::new_multi_decoder(archive)),
+        #[cfg(feature = "archive-zstd")]
+        "zst" => Box::new(
+            zstd::stream::read::Decoder::with_buffer(archive)
+                .expect("Could not create zstd decoder."),
+        ),
+        #[cfg(feature = "archive-bz2")]
+        "bz2" => Box::new(bzip2::read::MultiBzDecoder::new(archive)),
+        compression => panic!(
+            "{} archive compression {compression} for {}@{}",
+            color!(bright_red, "Unsupported"),
+            installer.meta.id,
+            installer.meta.version
+        ),
+    };
+    let mut archive = Archive::new(decoder);
+
+    let es = archive
+        .entries()
+        .expect("Cannot get entries from downloaded tar.");
+
+    for e in es {
+        let mut e = e.expect("Malformed entry in tarball.");
+
+        // Global pax headers only hold metadata and directories are created when needed,
+        // so they are never written out.
+        let entry_type = e.header().entry_type();
+        if entry_type.is_pax_global_extensions() || entry_type.is_dir() {
+            continue;
+        }
+
+        let str_name = e
+            .path()
+            .expect("Could not extract path from archive.")
+            .into_owned()
+            .into_os_string()
+            .into_string()
+            .expect("Archive has non utf-8 path.");
+
+        // Only regular files are allowed, no links, devices, or fifos
+        installer.check_entry_type(&str_name, entry_type.is_file(), &format!("{entry_type:?}"));
+
+        let entry_size = e.size();
+        installer.install(&str_name, entry_size, &mut e);
+    }
+}
+
+fn extract_zip(installer: &mut Installer, archive: File) {
+    let mut archive = ZipArchive::new(BufReader::new(archive)).expect("Malformed zip archive.");
+
+    for i in 0..archive.len() {
+        let mut e = archive.by_index(i).expect("Malformed entry in zip.");
+        if e.is_dir() {
+            continue;
+        }
+        let str_name = e.name().to_string();
+
+        // Zip only marks links and special files through unix modes
+        let mode = e.unix_mode().map_or(0, |m| m & 0o170_000);
+        installer.check_entry_type(
+            &str_name,
+            e.is_file() && (mode == 0 || mode == 0o100_000),
+            &format!("mode {mode:o}"),
+        );
+
+        let entry_size = e.size();
+        installer.install(&str_name, entry_size, &mut e);
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum EntryKind {
+    Bin,
+    Extra,
+    Completion,
+}
+
+/// Installs entries from an archive, keeping track of them across the whole archive.
+struct Installer<'a> {
+    meta: &'a Meta<'a>,
+    info: &'a InfoFileImm,
+    seen: HashSet<String>,
+    total_size: u64,
+    entry_limit: u64,
+    total_limit: u64,
+}
+impl<'a> Installer<'a> {
+    fn new(meta: &'a Meta<'a>, info: &'a InfoFileImm) -> Self {
+        Self {
+            meta,
+            info,
+            seen: HashSet::new(),
+            total_size: 0,
+            entry_limit: EXTRACT_ENTRY_LIMIT,
+            total_limit: EXTRACT_TOTAL_LIMIT,
+        }
+    }
+
+    fn extract(&mut self, archive: File) {
+        match self.info.archive.package.as_str() {
+            "tar" => extract_tar(self, archive),
+            "zip" => extract_zip(self, archive),
+            package => panic!(
+                "{} archive packaging {package} for {}@{}",
+                color!(bright_red, "Unsupported"),
+                self.meta.id,
+                self.meta.version
+            ),
+        }
+    }
+
+    fn check_entry_type(&self, str_name: &str, is_file: bool, entry_type: &str) {
+        assert!(
+            is_file,
+            "{} entry type ({entry_type}) for {str_name} in archive for {}@{}",
+            color!(bright_red, "Illegal"),
+            self.meta.id,
+            self.meta.version
+        );
+    }
+
+    /// Binaries are installed from the top of the archive,
+    /// anything else must be a declared extra or completion.
+    fn destination(&self, str_name: &str) -> (EntryKind, Option<PathBuf>) {
+        let meta = self.meta;
+        let info = self.info;
+
+        if !str_name.contains(std::path::is_separator) && Fetcher::is_bin(info, str_name) {
+            let mut path = meta.config.path.clone();
+            path.push(str_name);
+            (EntryKind::Bin, Some(path))
+        } else if let Some(path) = extra_path(meta, info, str_name) {
+            (EntryKind::Extra, Some(path))
+        } else if completions::is_completion(info, str_name) {
+            (EntryKind::Completion, completions::path(info, str_name))
+        } else {
+            panic!(
+                "{} file ({str_name}) in archive for {}@{}, it is not a binary, declared extra, or completion",
+                color!(bright_red, "Illegal"),
+                meta.id,
+                meta.version
+            )
+        }
+    }
+
+    fn install(&mut self, str_name: &str, entry_size: u64, entry: &mut dyn Read) {
+        let meta = self.meta;
+        let info = self.info;
+
+        let (kind, path) = self.destination(str_name);
+
+        assert!(
+            self.seen.insert(str_name.to_string()),
+            "{} entry ({str_name}) in archive for {}@{}",
+            color!(bright_red, "Duplicate"),
+            meta.id,
+            meta.version
+        );
+
+        // Check sizes before extracting anything
+        assert!(
+            entry_size <= self.entry_limit,
+            "{} ({str_name}) is {entry_size} bytes, over the limit of {} bytes for {}@{}",
+            color!(bright_red, "Entry too large"),
+            self.entry_limit,
+            meta.id,
+            meta.version
+        );
+        self.total_size = self.total_size.saturating_add(entry_size);
+        assert!(
+            self.total_size <= self.total_limit,
+            "{} is over the limit of {} bytes for {}@{}",
+            color!(bright_red, "Archive too large"),
+            self.total_limit,
+            meta.id,
+            meta.version
+        );
+
+        match kind {
+            EntryKind::Extra if meta.config.no_extras => return,
+            EntryKind::Completion if !meta.config.completions => return,
+            _ => {}
+        }
+        let path = path.unwrap_or_else(|| {
+            panic!("Could not find where to install {str_name}, try setting $XDG_DATA_HOME.")
+        });
+
+        let dir = path.parent().expect("Install path has no parent.");
+        if !meta.config.no_create_path && create_dir_all(dir).is_err() {
+            panic!("Could not create the directory '{}'.", dir.display());
+        } else if !dir.exists() {
+            panic!("Directory does not exist! '{}'.", dir.display());
+        }
+
+        // Write next to the install path, so nothing unverified gets installed
+        let tmp = NamedTempFile::new_in(dir).expect("Could not open temp file to write file to.");
+        let hashes = (kind == EntryKind::Bin && meta.config.hash_bins).then(|| {
+            info.bins_hashes
+                .get(str_name)
+                .unwrap_or_else(|| panic!("{str_name} is missing hashes."))
+        });
+        let mut writer = HashWriter::new(tmp, hashes, meta.config.hash_policy);
+
+        // Never trust the header alone, read at most the limit
+        let written = io::copy(&mut entry.take(self.entry_limit), &mut writer)
+            .expect("Could not extract file from archive.");
+        assert!(
+            written == entry_size,
+            "{} ({str_name}) size does not match its header for {}@{}",
+            color!(bright_red, "Entry"),
+            meta.id,
+            meta.version
+        );
+
+        let (tmp, hash) = writer.finish();
+        if hashes.is_some() {
+            Fetcher::verify_binary(meta, info, str_name, &hash);
+        }
+
+        // Attempt to add +x permission for binaries on unix platforms.
+        #[cfg(target_family = "unix")]
+        {
+            use std::{fs, os::unix::fs::PermissionsExt};
+            let mode = if kind == EntryKind::Bin { 0o755 } else { 0o644 };
+            if tmp
+                .as_file()
+                .set_permissions(fs::Permissions::from_mode(mode))
+                .is_err()
+            {
+                warn!(
+                    "Could not",
+                    "set mode {mode:o} for {}@{} file {str_name}", meta.id, meta.version
+                );
+            }
+        }
+
+        tmp.persist(&path)
+            .expect("Could not write file to install path.");
+
+        let abs = dunce::canonicalize(path).expect("Could not canonicalize install path.");
+
+        status!(bright_purple, "Installed", "{}", abs.display());
+
+        match kind {
+            EntryKind::Bin => events::binary_installed(meta, &abs.display().to_string()),
+            EntryKind::Extra => events::extra_installed(meta, &abs.display().to_string()),
+            EntryKind::Completion => {
+                events::completion_installed(meta, &abs.display().to_string());
+            }
+        }
+    }
+}
+
+/// Finds where a declared extra is installed under the prefix.
+/// Extras ending with `/` are directories, with all of their files installed.
+/// When more than one extra matches, the longest one is used.
+pub fn extra_path(meta: &Meta, info: &InfoFileImm, str_name: &str) -> Option<PathBuf> {
+    let (_, to, rest) = info
+        .extras
+        .iter()
+        .filter_map(|(from, to)| {
+            let rest = if from.ends_with('/') {
+                str_name.strip_prefix(from.as_str())?
+            } else if from.eq(str_name) {
+                Path::new(str_name).file_name()?.to_str()?
+            } else {
+                return None;
+            };
+            Some((from, to, rest))
+        })
+        .max_by_key(|(from, _, _)| from.len())?;
+
+    for p in [str_name, to.as_str(), rest] {
+        assert!(
+            is_contained(p),
+            "{} path ({p}) for extra in archive for {}@{}",
+            color!(bright_red, "Illegal"),
+            meta.id,
+            meta.version
+        );
+    }
+
+    let mut path = meta.config.prefix.clone();
+    path.push(to);
+    path.push(rest);
+    Some(path)
+}
+
+/// Only allows relative paths that cannot leave the directory they are pushed onto.
+fn is_contained(path: &str) -> bool {
+    !path.is_empty()
+        && Path::new(path)
+            .components()
+            .all(|c| matches!(c, Component::Normal(_)))
+}
+
+#[cfg(test)]
+mod test {
+    use std::{
+        fs::File,
+        io::{Cursor, Seek, Write},
+        path::Path,
+    };
+
+    use flate2::{write::GzEncoder, Compression};
+    use tar::{Builder, EntryType, Header};
+    use zip::{write::FileOptions, ZipWriter};
+
+    use super::{check_supported, extra_path, is_contained, Installer};
+    use crate::{
+        config::{self, Config},
+        data::{InfoFile, InfoFileArchiveV2, InfoFileImm, Meta},
+    };
+
+    fn config(dir: &Path) -> Config {
+        let path = dir.join("bin");
+        let prefix = dir.join("prefix");
+        config::from_args(&[
+            "--path",
+            path.to_str().unwrap(),
+            "--prefix",
+            prefix.to_str().unwrap(),
+            "pkg",
+        ])
+    }
+
+    fn info(package: &str, compression: &str) -> InfoFileImm {
+        let info: InfoFile = serde_json::from_str(include_str!("../test/info_3.json")).unwrap();
+        let mut info = InfoFileImm::convert(info, "TARGET");
+        info.archive.package = package.to_string();
+        info.archive.compression = compression.to_string();
+        info
+    }
+
+    fn archive_file(bytes: &[u8]) -> File {
+        let mut file = tempfile::tempfile().unwrap();
+        file.write_all(bytes).unwrap();
+        file.rewind().unwrap();
+        file
+    }
+
+    fn gz(bytes: &[u8]) -> Vec<u8> {
+        let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(bytes).unwrap();
+        encoder.finish().unwrap()
+    }
+
+    /// A tar with an entry for each of `(name, type, data)`, links point to `BINARY`.
+    fn tar(entries: &[(&str, EntryType, &[u8])]) -> Vec<u8> {
+        let mut builder = Builder::new(Vec::new());
+        for (name, entry_type, data) in entries {
+            let mut header = Header::new_gnu();
+            header.set_entry_type(*entry_type);
+            header.set_size(data.len() as u64);
+            header.set_mode(0o755);
+            if matches!(entry_type, EntryType::Symlink | EntryType::Link) {
+                header.set_link_name("BINARY").unwrap();
+            }
+            builder.append_data(&mut header, name, *data).unwrap();
+        }
+        builder.into_inner().unwrap()
+    }
+
+    /// Extracts an archive into a temp dir, with the limits of the installer lowered to `limit`.
+    fn extract_bytes(
+        package: &str,
+        compression: &str,
+        archive: &[u8],
+        limit: Option<u64>,
+    ) -> tempfile::TempDir {
+        let dir = tempfile::tempdir().unwrap();
+        let config = config(dir.path());
+        let meta = Meta::new("CRATES.IO ID", "VERSION", &config);
+        let info = info(package, compression);
+
+        let mut installer = Installer::new(&meta, &info);
+        if let Some(limit) = limit {
+            installer.entry_limit = limit;
+            installer.total_limit = limit;
+        }
+        installer.extract(archive_file(archive));
+        dir
+    }
+
+    fn extract_tar_gz(tar: &[u8], limit: Option<u64>) -> tempfile::TempDir {
+        extract_bytes("tar", "gz", &gz(tar), limit)
+    }
+
+    /// A zip with a file for each of `(name, data)`, and a symlink to `BINARY` for `link`.
+    fn zip(files: &[(&str, &[u8])], link: Option<&str>) -> Vec<u8> {
+        let mut writer = ZipWriter::new(Cursor::new(Vec::new()));
+        for (name, data) in files {
+            writer
+                .start_file(*name, FileOptions::default().unix_permissions(0o755))
+                .unwrap();
+            writer.write_all(data).unwrap();
+        }
+        if let Some(link) = link {
+            writer
+                .add_symlink(link, "BINARY", FileOptions::default())
+                .unwrap();
+        }
+        writer.finish().unwrap().into_inner()
+    }
+
+    fn assert_installed(dir: &tempfile::TempDir) {
+        let dir = dir.path();
+        assert_eq!(std::fs::read(dir.join("bin/BINARY")).unwrap(), b"bin");
+        assert_eq!(
+            std::fs::read(dir.join("prefix/share/man/man1/BINARY.1")).unwrap(),
+            b"man"
+        );
+    }
+
+    #[test]
+    fn test_extract_tar() {
+        let dir = extract_tar_gz(
+            &tar(&[
+                ("BINARY", EntryType::Regular, b"bin"),
+                ("doc/BINARY.1", EntryType::Regular, b"man"),
+                ("lib/a/b.so", EntryType::Regular, b"lib"),
+            ]),
+            None,
+        );
+
+        let dir = dir.path();
+        assert_eq!(std::fs::read(dir.join("bin/BINARY")).unwrap(), b"bin");
+        assert_eq!(
+            std::fs::read(dir.join("prefix/share/man/man1/BINARY.1")).unwrap(),
+            b"man"
+        );
+        assert_eq!(
+            std::fs::read(dir.join("prefix/lib/BINARY/a/b.so")).unwrap(),
+            b"lib"
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "entry type (Symlink)")]
+    fn test_extract_symlink() {
+        extract_tar_gz(&tar(&[("BINARY?", EntryType::Symlink, b"")]), None);
+    }
+
+    #[test]
+    #[should_panic(expected = "entry type (Link)")]
+    fn test_extract_hardlink() {
+        extract_tar_gz(&tar(&[("BINARY?", EntryType::Link, b"")]), None);
+    }
+
+    #[test]
+    #[should_panic(expected = "entry type (Char)")]
+    fn test_extract_device() {
+        extract_tar_gz(&tar(&[("BINARY?", EntryType::Char, b"")]), None);
+    }
+
+    #[test]
+    #[should_panic(expected = "Duplicate")]
+    fn test_extract_duplicate() {
+        extract_tar_gz(
+            &tar(&[
+                ("BINARY", EntryType::Regular, b"bin"),
+                ("BINARY", EntryType::Regular, b"evil"),
+            ]),
+            None,
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "not a binary, declared extra, or completion")]
+    fn test_extract_undeclared() {
+        extract_tar_gz(&tar(&[("other", EntryType::Regular, b"bin")]), None);
+    }
+
+    #[test]
+    #[should_panic(expected = "Entry too large")]
+    fn test_extract_entry_limit() {
+        extract_tar_gz(&tar(&[("BINARY", EntryType::Regular, b"12345")]), Some(4));
+    }
+
+    #[test]
+    #[should_panic(expected = "Archive too large")]
+    fn test_extract_total_limit() {
+        extract_tar_gz(
+            &tar(&[
+                ("BINARY", EntryType::Regular, b"123"),
+                ("BINARY?", EntryType::Regular, b"123"),
+            ]),
+            Some(4),
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "size does not match its header")]
+    fn test_extract_short_entry() {
+        // The header claims more data than the archive has
+        let mut header = Header::new_gnu();
+        header.set_path("BINARY").unwrap();
+        header.set_entry_type(EntryType::Regular);
+        header.set_size(1000);
+        header.set_cksum();
+        let mut tar = header.as_bytes().to_vec();
+        tar.extend_from_slice(b"bin");
+
+        extract_tar_gz(&tar, None);
+    }
+
+    #[test]
+    fn test_extract_zip() {
+        let dir = extract_bytes(
+            "zip",
+            "",
+            &zip(&[("BINARY", b"bin"), ("doc/BINARY.1", b"man")], None),
+            None,
+        );
+        assert_installed(&dir);
+    }
+
+    #[test]
+    #[should_panic(expected = "entry type (mode 120000)")]
+    fn test_extract_zip_symlink() {
+        extract_bytes("zip", "", &zip(&[], Some("BINARY?")), None);
+    }
+
+    #[test]
+    fn test_check_supported() {
+        let config = config(Path::new("/"));
+        let meta = Meta::new("CRATES.IO ID", "VERSION", &config);
+        for (package, compression) in [("tar", "gz"), ("zip", ""), ("zip", "deflate")] {
+            check_supported(
+                &meta,
+                &InfoFileArchiveV2 {
+                    compression: compression.to_string(),
+                    package: package.to_string(),
+                },
+            );
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "archive packaging rar")]
+    fn test_check_unsupported() {
+        let config = config(Path::new("/"));
+        let meta = Meta::new("CRATES.IO ID", "VERSION", &config);
+        check_supported(
+            &meta,
+            &InfoFileArchiveV2 {
+                compression: "gz".to_string(),
+                package: "rar".to_string(),
+            },
+        );
+    }
+
+    fn release_tar() -> Vec<u8> {
+        tar(&[
+            ("BINARY", EntryType::Regular, b"bin"),
+            ("doc/BINARY.1", EntryType::Regular, b"man"),
+        ])
+    }
+
+    #[cfg(feature = "archive-xz")]
+    #[test]
+    fn test_extract_tar_xz() {
+        let mut encoder = xz2::write::XzEncoder::new(Vec::new(), 6);
+        encoder.write_all(&release_tar()).unwrap();
+        let archive = encoder.finish().unwrap();
+        assert_installed(&extract_bytes("tar", "xz", &archive, None));
+    }
+
+    #[cfg(feature = "archive-zstd")]
+    #[test]
+    fn test_extract_tar_zstd() {
+        let archive = zstd::stream::encode_all(release_tar().as_slice(), 0).unwrap();
+        assert_installed(&extract_bytes("tar", "zst", &archive, None));
+    }
+
+    #[cfg(feature = "archive-bz2")]
+    #[test]
+    fn test_extract_tar_bz2() {
+        let mut encoder = bzip2::write::BzEncoder::new(Vec::new(), bzip2::Compression::default());
+        encoder.write_all(&release_tar()).unwrap();
+        let archive = encoder.finish().unwrap();
+        assert_installed(&extract_bytes("tar", "bz2", &archive, None));
+    }
+
+    /// Compressions of enabled features are supported, the rest ask for their feature.
+    #[test]
+    fn test_check_supported_features() {
+        let config = config(Path::new("/"));
+        let meta = Meta::new("CRATES.IO ID", "VERSION", &config);
+        for (compression, feature, enabled) in [
+            ("xz", "archive-xz", cfg!(feature = "archive-xz")),
+            ("zst", "archive-zstd", cfg!(feature = "archive-zstd")),
+            ("bz2", "archive-bz2", cfg!(feature = "archive-bz2")),
+        ] {
+            let archive = InfoFileArchiveV2 {
+                compression: compression.to_string(),
+                package: "tar".to_string(),
+            };
+            let checked = std::panic::catch_unwind(|| check_supported(&meta, &archive));
+            if enabled {
+                assert!(checked.is_ok());
+            } else {
+                let message = crate::coloring::panic_message(checked.unwrap_err().as_ref());
+                assert!(message.contains(&format!("requires the {feature} feature")));
+            }
+        }
+    }
+
+    fn extra(extras: &[(&str, &str)], str_name: &str) -> Option<std::path::PathBuf> {
+        let config = config(Path::new("/root"));
+        let meta = Meta::new("CRATES.IO ID", "VERSION", &config);
+        let mut info = info("tar", "gz");
+        info.extras = extras
+            .iter()
+            .map(|(from, to)| ((*from).to_string(), (*to).to_string()))
+            .collect();
+        extra_path(&meta, &info, str_name)
+    }
+
+    #[test]
+    fn test_extra_path() {
+        let prefix = Path::new("/root/prefix");
+
+        // Files keep their name, directories keep the path under them
+        let extras = [("doc/BINARY.1", "share/man/man1"), ("lib/", "lib/BINARY")];
+        assert_eq!(
+            extra(&extras, "doc/BINARY.1"),
+            Some(prefix.join("share/man/man1/BINARY.1"))
+        );
+        assert_eq!(
+            extra(&extras, "lib/a/b.so"),
+            Some(prefix.join("lib/BINARY/a/b.so"))
+        );
+        assert_eq!(extra(&extras, "doc/other.1"), None);
+        assert_eq!(extra(&extras, "libs/b.so"), None);
+
+        // The longest match wins, whatever the order
+        for extras in [
+            [("share/", "share"), ("share/man/", "man")],
+            [("share/man/", "man"), ("share/", "share")],
+        ] {
+            assert_eq!(
+                extra(&extras, "share/man/a.1"),
+                Some(prefix.join("man/a.1"))
+            );
+            assert_eq!(
+                extra(&extras, "share/doc/a.md"),
+                Some(prefix.join("share/doc/a.md"))
+            );
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "path (../a) for extra")]
+    fn test_extra_path_parent() {
+        extra(&[("lib/", "../a")], "lib/b.so");
+    }
+
+    #[test]
+    #[should_panic(expected = "path (/etc) for extra")]
+    fn test_extra_path_absolute() {
+        extra(&[("lib/", "/etc")], "lib/b.so");
+    }
+
+    #[test]
+    #[should_panic(expected = "path () for extra")]
+    fn test_extra_path_empty() {
+        extra(&[("lib/", "lib")], "lib/");
+    }
+
+    #[test]
+    fn test_is_contained() {
+        assert!(is_contained("a"));
+        assert!(is_contained("a/b/c"));
+        assert!(!is_contained(""));
+        assert!(!is_contained("."));
+        assert!(!is_contained("a/../../b"));
+        assert!(!is_contained("/a"));
+        assert!(!is_contained("./a"));
+    }
+}
@@ -1,36 +1,321 @@
 use std::{
-    fs::{create_dir_all, File},
-    io::Write,
+    fs::{self, create_dir_all, File},
+    io::{self, Read, Seek, Write},
+    path::PathBuf,
+    time::{Instant, SystemTime, UNIX_EPOCH},
 };
 
+#[cfg(feature = "sigstore")]
+use crate::sigstore::Sigstore;
 use crate::{
     color,
     config::Config,
-    data::{HashType, Hashes, HashesFile, HashesFileV1, InfoFile, InfoFileImm, Meta, ReportType},
-    events,
-    interact::{self, Interact},
+    data::{
+        pick_target, usable_keys, HashPolicy, HashType, Hashes, HashesFile, HashesFileV1, InfoFile,
+        InfoFileImm, Meta, PubKey, ReportType,
+    },
+    events, extract,
+    hashing::{self, HashWriter},
+    interact::{self, Blob, Interact},
+    keys, progress, status, verbose, warn, APPLICATION, ORG, QUALIFIER,
 };
+use directories::ProjectDirs;
+use semver::Version;
 use ureq::Agent;
 
+/// How far in the future (in seconds) a signature timestamp can be, to allow for clock differences.
+const SIG_CLOCK_SKEW: u64 = 60 * 60;
+
+/// How many bytes are downloaded between progress events.
+const PROGRESS_STEP: u64 = 1_048_576; // 1 MB
+
+/// Counts bytes read, printing progress events and drawing a progress bar along the way.
+struct ProgressReader<'a, R: Read> {
+    meta: &'a Meta<'a>,
+    file: &'a str,
+    inner: R,
+    bytes: u64,
+    bar: Option<progress::Bar>,
+}
+impl<'a, R: Read> ProgressReader<'a, R> {
+    fn new(meta: &'a Meta<'a>, file: &'a str, inner: R, len: Option<u64>) -> Self {
+        let bar = progress::Bar::new(meta.config, &format!("{}@{}", meta.id, meta.version), len);
+        Self {
+            meta,
+            file,
+            inner,
+            bytes: 0,
+            bar,
+        }
+    }
+}
+impl<R: Read> Read for ProgressReader<'_, R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n = self.inner.read(buf)?;
+        let before = self.bytes;
+        self.bytes += n as u64;
+        if before / PROGRESS_STEP != self.bytes / PROGRESS_STEP {
+            events::download_progress(self.meta, self.file, self.bytes);
+        }
+        if let Some(bar) = &mut self.bar {
+            bar.set(self.bytes);
+        }
+        Ok(n)
+    }
+}
+impl<R: Read> ProgressReader<'_, R> {
+    /// Clears the progress bar, which is also done when dropped.
+    fn finish(&mut self) {
+        if let Some(bar) = self.bar.take() {
+            bar.finish();
+        }
+    }
+}
+impl<R: Read> Drop for ProgressReader<'_, R> {
+    fn drop(&mut self) {
+        self.finish();
+    }
+}
+
+/// A directory in the data directory for things kept per index.
+pub fn index_data_path(config: &Config, dir: &str) -> Option<PathBuf> {
+    ProjectDirs::from(QUALIFIER, ORG, APPLICATION).map(|project| {
+        let mut path = PathBuf::from(project.data_dir());
+        path.push(dir);
+        path.push(index_dir_name(&config.index));
+        path
+    })
+}
+
+/// The sha256 of the index, so different indexes never share a directory.
+fn index_dir_name(index: &str) -> String {
+    let mut hasher = hashing::hasher(HashType::Sha256);
+    hasher.update(index.as_bytes());
+    const_hex::encode(hasher.finalize())
+}
+
+/// Where the latest version seen of a crate is kept, per index.
+fn last_seen_path(config: &Config, id: &str) -> Option<PathBuf> {
+    index_data_path(config, "latest").map(|mut path| {
+        path.push(id);
+        path
+    })
+}
+
+/// Where the newest signature timestamp seen for a file of a crate version is kept, per index.
+fn timestamp_path(config: &Config, id: &str, version: &str, file: &str) -> Option<PathBuf> {
+    index_data_path(config, "timestamps").map(|mut path| {
+        path.push(id);
+        path.push(version);
+        path.push(file);
+        path
+    })
+}
+
+/// Checks if `version` is older than `last_seen`, versions that are not semver cannot be compared.
+fn is_older(version: &str, last_seen: &str) -> bool {
+    match (Version::parse(version), Version::parse(last_seen)) {
+        (Ok(version), Ok(last_seen)) => version < last_seen,
+        _ => false,
+    }
+}
+
+/// The `timestamp:` in a verified trusted comment, if there is one.
+/// `what` names the signed file in errors.
+pub fn trusted_timestamp(comment: &str, what: &str) -> Option<u64> {
+    TrustedComment::parse(comment)
+        .timestamp
+        .map(|t| check_future(t, what))
+}
+
+fn check_future(timestamp: &str, what: &str) -> u64 {
+    let timestamp: u64 = timestamp.parse().unwrap_or_else(|_| {
+        panic!(
+            "{} timestamp in signature of {what}.",
+            color!(bright_red, "Malformed")
+        )
+    });
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .expect("System time is before the unix epoch.")
+        .as_secs();
+    assert!(
+        timestamp <= now.saturating_add(SIG_CLOCK_SKEW),
+        "{} timestamp in signature of {what} is in the future.",
+        color!(bright_red, "Invalid")
+    );
+    timestamp
+}
+
+/// Refuses `timestamp` if it is older than the one stored at `path`, then stores it if newer.
+/// `what` names the signed file in errors.
+pub fn check_replay(path: Option<PathBuf>, what: &str, timestamp: u64) {
+    let Some(path) = path else {
+        warn!(
+            "Could not",
+            "find where to store signature timestamps, skipping replay check for {what}."
+        );
+        return;
+    };
+
+    if let Some(last_seen) = fs::read_to_string(&path)
+        .ok()
+        .and_then(|s| s.trim().parse::<u64>().ok())
+    {
+        assert!(
+            timestamp >= last_seen,
+            "{} signature of {what} is older than the last seen. {timestamp} < {last_seen}",
+            color!(bright_red, "Refusing replayed")
+        );
+        if timestamp == last_seen {
+            return;
+        }
+    }
+
+    if path
+        .parent()
+        .map_or(true, |dir| create_dir_all(dir).is_err())
+        || fs::write(&path, timestamp.to_string()).is_err()
+    {
+        warn!(
+            "Could not",
+            "store the signature timestamp of {what} at '{}'.",
+            path.display()
+        );
+    }
+}
+
+/// Known key:value pairs from a minisign trusted comment, separated by tabs or spaces.
+#[derive(Debug, Default, PartialEq, Eq)]
+struct TrustedComment<'a> {
+    id: Option<&'a str>,
+    version: Option<&'a str>,
+    timestamp: Option<&'a str>,
+}
+impl<'a> TrustedComment<'a> {
+    fn parse(comment: &'a str) -> Self {
+        let mut parsed = Self::default();
+        for (key, value) in comment
+            .split(['\t', ' '])
+            .filter_map(|pair| pair.split_once(':'))
+        {
+            match key {
+                "id" => parsed.id = Some(value),
+                "version" => parsed.version = Some(value),
+                "timestamp" => parsed.timestamp = Some(value),
+                _ => {}
+            }
+        }
+        parsed
+    }
+}
+
 pub struct Fetcher {
     interact: Box<dyn Interact>,
+    keys: Option<Vec<PubKey>>,
+    #[cfg(feature = "sigstore")]
+    sigstore: Option<Sigstore>,
 }
 impl Fetcher {
     pub fn new(config: &Config, agent: Agent) -> Self {
-        let interact = interact::create_interactive(&config.index, config.auth.as_ref(), agent);
-        Self { interact }
+        let interact = interact::create_interactive(
+            &config.index,
+            config.auth.as_ref(),
+            config.max_download_size,
+            agent,
+        );
+        Self {
+            interact,
+            keys: None,
+            #[cfg(feature = "sigstore")]
+            sigstore: None,
+        }
+    }
+
+    pub fn get_latest(&mut self, id: &str, config: &Config) -> String {
+        let latest = self.fetch_latest(id);
+        let meta = &Meta::new(id, &latest, config);
+
+        // stable-index/$ID.sigstore.json and test
+        if !config.no_sig && config.sigstore.is_some() {
+            let bundle = self
+                .interact
+                .get_stable_index(&format!("{id}.sigstore.json"));
+            self.verify_bundle(
+                meta,
+                "stable-index",
+                bundle,
+                &hashing::sha256(latest.as_bytes()),
+            );
+        }
+        // stable-index/$ID.minisig and test
+        else if !config.no_sig {
+            let interact = &mut self.interact;
+            let sigs = keys::fetch_sigs(config.sig_threshold, &format!("{id}.minisig"), |f| {
+                interact.get_stable_index(f)
+            });
+            assert!(
+                !sigs.is_empty(),
+                "Could not force sig for index {}. stable-index is not signed for {id}.",
+                config.index
+            );
+            self.verify_sig(meta, "stable-index", &sigs, &latest);
+        }
+
+        Self::check_last_seen(meta);
+
+        latest
     }
 
-    pub fn get_latest(&mut self, id: &str) -> String {
-        self.fetch_latest(id)
+    /// Refuses to go back to a version older than the latest one seen before for this index,
+    /// since an old stable-index could still be validly signed.
+    fn check_last_seen(meta: &Meta) {
+        let Some(path) = last_seen_path(meta.config, meta.id) else {
+            warn!(
+                "Could not",
+                "find where to store the latest version of {}, skipping downgrade check.", meta.id
+            );
+            return;
+        };
+
+        if let Ok(last_seen) = fs::read_to_string(&path) {
+            let last_seen = last_seen.trim();
+            assert!(
+                !is_older(meta.version, last_seen),
+                "{} latest version {} of {} is older than the last seen {last_seen}.",
+                color!(bright_red, "Refusing downgrade,"),
+                meta.version,
+                meta.id
+            );
+            if last_seen.eq(meta.version) {
+                return;
+            }
+        }
+
+        if path
+            .parent()
+            .map_or(true, |dir| create_dir_all(dir).is_err())
+            || fs::write(&path, meta.version).is_err()
+        {
+            warn!(
+                "Could not",
+                "store the latest version of {} at '{}'.",
+                meta.id,
+                path.display()
+            );
+        }
     }
 
-    pub fn download_info(&mut self, meta: &Meta) -> InfoFileImm {
-        eprintln!(
-            "{} info for {}@{}...",
-            color!(bright_blue, "Fetching"),
+    /// Fails with the targets the crate was built for, if none of the configured targets were.
+    /// Uninstalling still succeeds then, with the first configured target.
+    #[allow(clippy::too_many_lines)]
+    pub fn download_info(&mut self, meta: &Meta) -> Result<InfoFileImm, Vec<String>> {
+        status!(
+            bright_blue,
+            "Fetching",
+            "info for {}@{}...",
             meta.id,
-            meta.version,
+            meta.version
         );
 
         // info.json
@@ -44,7 +329,14 @@ impl Fetcher {
 
         let info: InfoFile = serde_json::from_str(raw_info_file)
             .unwrap_or_else(|_| panic!("info.json is malformed for {}@{}", meta.id, meta.version));
-        let mut info: InfoFileImm = InfoFileImm::convert(info, &meta.config.target);
+        let built_for = info.built_for();
+        let target = match pick_target(&meta.config.targets, &built_for) {
+            Some(target) => target,
+            None if meta.config.uninstall => &meta.config.targets[0],
+            None => return Err(built_for.into_iter().map(ToString::to_string).collect()),
+        };
+        let supported = built_for.contains(&target.as_str());
+        let mut info: InfoFileImm = InfoFileImm::convert(info, target);
 
         assert!(
             info.id.eq(meta.id),
@@ -61,16 +353,11 @@ impl Fetcher {
             info.version
         );
 
-        // check if compression is supported
-        assert!(
-            info.archive.compression.eq("gz"),
-            "{}@{} does not support compression gzip",
-            meta.id,
-            meta.version,
-        );
+        // check if packaging and compression are supported
+        extract::check_supported(meta, &info.archive);
 
         // check if binary does not exist if safe mode is on
-        if meta.config.safe && !(meta.config.ci || meta.config.update) {
+        if meta.config.safe && !(meta.config.ci || meta.config.update || meta.config.uninstall) {
             for bin in &info.bins {
                 let mut path = meta.config.path.clone();
                 path.push(bin);
@@ -88,14 +375,15 @@ impl Fetcher {
             }
         }
 
-        if !meta.config.no_hash {
+        if !meta.config.no_hash && supported {
             if let Some(ref polyfill) = info.polyfill {
-                eprintln!(
-                    "{} hashes for {}@{} with target {}...",
-                    color!(bright_blue, "Fetching"),
+                status!(
+                    bright_blue,
+                    "Fetching",
+                    "hashes for {}@{} with target {}...",
                     meta.id,
                     meta.version,
-                    &meta.config.target
+                    &info.target
                 );
 
                 // hashes.json
@@ -103,16 +391,21 @@ impl Fetcher {
 
                 // hashes.json.minisig and test
                 if !meta.config.no_sig {
-                    if let Some(sig_file) = polyfill.hash_file_sig.clone() {
-                        let v =
-                            self.verify_file(meta, &polyfill.hash_file, &sig_file, raw_hashes_file);
-                        events::hashes_verify(meta, v);
-                    } else {
-                        panic!(
-                            "Could not force sig for index {}. hashes.json is not signed for {}@{}.",
-                            meta.config.index, meta.id, meta.version
-                        );
-                    }
+                    let sig_file = polyfill.hash_file_sig.clone();
+                    assert!(
+                        sig_file.is_some() || meta.config.sigstore.is_some(),
+                        "Could not force sig for index {}. hashes.json is not signed for {}@{}.",
+                        meta.config.index,
+                        meta.id,
+                        meta.version
+                    );
+                    let v = self.verify_file(
+                        meta,
+                        &polyfill.hash_file,
+                        &sig_file.unwrap_or_default(),
+                        raw_hashes_file,
+                    );
+                    events::hashes_verify(meta, v);
                 }
 
                 let hashes: HashesFile =
@@ -125,8 +418,8 @@ impl Fetcher {
                 let hashes: HashesFileV1 = hashes.into();
                 let hashes = hashes
                     .hashes
-                    .get(&meta.config.target)
-                    .unwrap_or_else(|| panic!("No hashes for target {}", meta.config.target));
+                    .get(&info.target)
+                    .unwrap_or_else(|| panic!("No hashes for target {}", info.target));
 
                 info.archive_hashes.clone_from(&hashes.archive);
                 info.bins_hashes.clone_from(&hashes.bins);
@@ -134,35 +427,101 @@ impl Fetcher {
         }
 
         // check if target is supported, based on hash
-        if !meta.config.no_hash {
+        if !meta.config.no_hash && supported {
             assert!(
                 !info.archive_hashes.is_empty(),
                 "{}@{} does {} target {}, due to empty archive hashes",
                 meta.id,
                 meta.version,
                 color!(bright_red, "not support"),
-                meta.config.target
+                info.target
             );
         }
 
-        info
+        Ok(info)
     }
 
-    pub fn download_blob(&mut self, meta: &Meta, info: &InfoFileImm) -> Vec<u8> {
-        // tar
-        eprintln!(
-            "{} {}@{} for target {}...",
-            color!(bright_yellow, "Downloading"),
+    pub fn download_blob(&mut self, meta: &Meta, info: &InfoFileImm) -> File {
+        // archive
+        status!(
+            bright_yellow,
+            "Downloading",
+            "{}@{} for target {}...",
             meta.id,
             meta.version,
-            &meta.config.target
+            &info.target
+        );
+        events::download_started(meta, &info.archive_name);
+        let (blob, len) = self.fetch_blob(meta.id, meta.version, &info.archive_name);
+        let limit = meta.config.max_download_size;
+        if let (Some(limit), Some(len)) = (limit, len) {
+            assert!(
+                len <= limit,
+                "{} download archive for {}@{}, it is {len} bytes, over --max-download-size of {limit} bytes.",
+                color!(bright_red, "Could not"),
+                meta.id,
+                meta.version
+            );
+        }
+        let mut blob = ProgressReader::new(meta, &info.archive_name, blob, len);
+
+        // Stream into a temp file, hashing along the way
+        let file = tempfile::tempfile().expect("Could not create temporary file for archive.");
+        let hashes = (!meta.config.no_hash).then_some(&info.archive_hashes);
+        if let Some(hashes) = hashes {
+            verbose!(
+                bright_white,
+                "Hashing",
+                "{} archive with {} (policy {}).",
+                info.target,
+                hashing::names(&hashing::select(hashes, meta.config.hash_policy)),
+                String::from(meta.config.hash_policy)
+            );
+        }
+        let mut writer = HashWriter::new(file, hashes, meta.config.hash_policy);
+        let started = Instant::now();
+        let copied = io::copy(&mut blob, &mut writer);
+        blob.finish();
+        copied.unwrap_or_else(|err| {
+            panic!(
+                "Could not download archive for {}@{}. {err}{}",
+                meta.id,
+                meta.version,
+                limit
+                    .map(|l| format!(" (--max-download-size is {l} bytes)"))
+                    .unwrap_or_default()
+            )
+        });
+        let (mut file, hashes) = writer.finish();
+        verbose!(
+            bright_white,
+            "Downloaded",
+            "{} bytes in {:.2?}.",
+            blob.bytes,
+            started.elapsed()
         );
-        let tar_bytes = self.fetch_blob(meta.id, meta.version, &info.archive_name);
+        events::download_finished(meta, &info.archive_name, blob.bytes);
 
         // test hashes
-        Self::verify_archive(meta, info, &tar_bytes);
+        Self::verify_archive(meta, info, &hashes);
+
+        // $ARCHIVE.sigstore.json and test, so upstream signatures can be mirrored
+        if !meta.config.no_sig && meta.config.sigstore.is_some() {
+            file.rewind()
+                .expect("Could not rewind downloaded archive file.");
+            let digest =
+                hashing::sha256_reader(&mut file).expect("Could not read downloaded archive file.");
+            let bundle = self.interact.get_str(
+                meta.id,
+                meta.version,
+                &format!("{}.sigstore.json", info.archive_name),
+            );
+            self.verify_bundle(meta, &info.archive_name, bundle, &digest);
+        }
 
-        tar_bytes
+        file.rewind()
+            .expect("Could not rewind downloaded archive file.");
+        file
     }
 
     pub fn is_bin(info: &InfoFileImm, bin_name: &str) -> bool {
@@ -175,7 +534,7 @@ impl Fetcher {
             return;
         }
 
-        eprintln!("{} reports... ", color!(bright_blue, "Getting"));
+        status!(bright_blue, "Getting", "reports... ");
 
         for report in &meta.config.reports {
             let report_name = match report {
@@ -200,17 +559,23 @@ impl Fetcher {
                             match File::create(&dir) {
                                 Ok(mut file) => match file.write(raw_str.as_bytes()) {
                                     Ok(_) => {
-                                        events::wrote_report(meta, report.into());
+                                        events::wrote_report(
+                                            meta,
+                                            report.into(),
+                                            &dir.display().to_string(),
+                                        );
                                     }
                                     Err(_) => {
-                                        eprintln!("Could not write to {report_name} file.");
+                                        warn!("Could not", "write to {report_name} file.");
                                     }
                                 },
-                                Err(_) => eprintln!("Could not create {report_name} file."),
+                                Err(_) => {
+                                    warn!("Could not", "create {report_name} file.");
+                                }
                             }
                         }
                         Err(_) => {
-                            eprintln!("Could not create directories for {report_name}.");
+                            warn!("Could not", "create directories for {report_name}.");
                         }
                     }
                 }
@@ -230,42 +595,66 @@ impl Fetcher {
         self.interact.get_str(id, version, file).unwrap()
     }
 
-    fn fetch_blob(&mut self, id: &str, version: &str, file: &str) -> Vec<u8> {
+    fn fetch_blob(&mut self, id: &str, version: &str, file: &str) -> Blob {
         self.interact.get_blob(id, version, file).unwrap()
     }
 
     fn verify_file(&mut self, meta: &Meta, file: &str, sig_file: &str, raw_file: &str) -> bool {
-        use minisign_verify::{PublicKey, Signature};
+        if meta.config.sigstore.is_some() {
+            let bundle =
+                self.interact
+                    .get_str(meta.id, meta.version, &format!("{file}.sigstore.json"));
+            return self.verify_bundle(meta, file, bundle, &hashing::sha256(raw_file.as_bytes()));
+        }
+
+        let interact = &mut self.interact;
+        let sigs = keys::fetch_sigs(meta.config.sig_threshold, sig_file, |f| {
+            interact.get_str(meta.id, meta.version, f)
+        });
+        self.verify_sig(meta, file, &sigs, raw_file)
+    }
+
+    fn verify_sig(&mut self, meta: &Meta, file: &str, sigs: &[String], raw_file: &str) -> bool {
+        let threshold = meta.config.sig_threshold;
+        let keys = self
+            .keys
+            .get_or_insert_with(|| keys::load(meta.config, self.interact.as_mut()));
+        let keys = usable_keys(keys, &keys::today());
 
         assert!(
-            !meta.config.pub_keys.is_empty(),
-            "{} for index '{}'. Please add one with --pub-key or use --no-verify.",
-            color!(bright_red, "No public key(s)"),
+            !keys.is_empty(),
+            "{} for index '{}'. Please add one with --pub-key or use --no-sig.",
+            color!(bright_red, "No usable public key(s)"),
             meta.config.index
         );
 
-        let sig = &self.fetch_str(meta.id, meta.version, sig_file);
-        let signature = Signature::decode(sig).expect("Signature was malformed.");
-
-        let mut verified = false;
-        for key in &meta.config.pub_keys {
-            let pk = PublicKey::from_base64(key).expect("Public key was malformed.");
-            if pk.verify(raw_file.as_bytes(), &signature, false).is_ok() {
-                verified = true;
-                break;
-            }
-        }
+        let (signers, signatures) = keys::verify_minisigs(&keys, sigs, raw_file);
+        let verified = signers >= threshold;
 
         if verified {
-            eprintln!(
-                "{} {file} for {}@{} with minisign.",
-                color!(bright_white, "Verified"),
+            // The trusted comment is covered by the signature, so it can be checked once verified
+            let newest = signatures
+                .iter()
+                .filter_map(|s| Self::verify_trusted_comment(meta, file, s.trusted_comment()))
+                .max();
+            if let Some(timestamp) = newest {
+                Self::check_timestamp(meta, file, timestamp);
+            }
+            status!(
+                bright_white,
+                "Verified",
+                "{file} for {}@{} with minisign{}.",
                 meta.id,
-                meta.version
+                meta.version,
+                if threshold > 1 {
+                    format!(" ({signers} keys)")
+                } else {
+                    String::new()
+                }
             );
         } else {
             panic!(
-                "{} verify {file} for {}@{}.",
+                "{} verify {file} for {}@{}, it is signed by {signers} of {threshold} required keys.",
                 color!(bright_red, "Could not"),
                 meta.id,
                 meta.version
@@ -275,202 +664,312 @@ impl Fetcher {
         verified
     }
 
-    fn verify_archive(meta: &Meta, info: &InfoFileImm, bytes: &[u8]) {
-        Self::verify_bytes(
+    #[cfg(feature = "sigstore")]
+    fn verify_bundle(
+        &mut self,
+        meta: &Meta,
+        file: &str,
+        bundle: anyhow::Result<String>,
+        digest: &[u8],
+    ) -> bool {
+        let policy = meta
+            .config
+            .sigstore
+            .as_ref()
+            .expect("Sigstore policy is set.");
+        let bundle = bundle.unwrap_or_else(|_| {
+            panic!(
+                "Could not force sig for index {}. {file} has no sigstore bundle for {}@{}.",
+                meta.config.index, meta.id, meta.version
+            )
+        });
+
+        let sigstore = self.sigstore.get_or_insert_with(|| Sigstore::new(policy));
+        if let Err(err) = sigstore.verify(&bundle, digest) {
+            panic!(
+                "{} verify {file} for {}@{} with sigstore.\n{err:#}",
+                color!(bright_red, "Could not"),
+                meta.id,
+                meta.version
+            );
+        }
+
+        status!(
+            bright_white,
+            "Verified",
+            "{file} for {}@{} with sigstore ({}).",
+            meta.id,
+            meta.version,
+            policy.identity
+        );
+        true
+    }
+
+    #[cfg(not(feature = "sigstore"))]
+    #[allow(clippy::unused_self)]
+    #[allow(clippy::needless_pass_by_ref_mut)]
+    fn verify_bundle(
+        &mut self,
+        _meta: &Meta,
+        _file: &str,
+        _bundle: anyhow::Result<String>,
+        _digest: &[u8],
+    ) -> bool {
+        unreachable!("Sigstore policies are only allowed with the sigstore feature.")
+    }
+
+    /// Indexes may put `id:`, `version:`, and `timestamp:` in the trusted comment,
+    /// which stops a signed file from another crate or version from being replayed.
+    /// Returns the timestamp, if there is one.
+    fn verify_trusted_comment(meta: &Meta, file: &str, comment: &str) -> Option<u64> {
+        let comment = TrustedComment::parse(comment);
+
+        for (key, value, expected) in [
+            ("id", comment.id, meta.id),
+            ("version", comment.version, meta.version),
+        ] {
+            if let Some(value) = value {
+                assert!(
+                    value.eq(expected),
+                    "{} {key} in signature of {file} for {}@{}. {value} != {expected}",
+                    color!(bright_red, "Mismatched"),
+                    meta.id,
+                    meta.version
+                );
+            }
+        }
+
+        comment
+            .timestamp
+            .map(|t| check_future(t, &format!("{file} for {}@{}", meta.id, meta.version)))
+    }
+
+    /// Refuses a signature older than the newest one seen before for the same file,
+    /// since a stale file could still be validly signed.
+    fn check_timestamp(meta: &Meta, file: &str, timestamp: u64) {
+        check_replay(
+            timestamp_path(meta.config, meta.id, meta.version, file),
+            &format!("{file} for {}@{}", meta.id, meta.version),
+            timestamp,
+        );
+    }
+
+    fn verify_archive(meta: &Meta, info: &InfoFileImm, hashes: &[(HashType, String)]) {
+        if Self::verify_hash(
             meta,
             &info.archive_hashes,
-            &format!("{} archive", &meta.config.target),
-            bytes,
-        );
+            &format!("{} archive", &info.target),
+            hashes,
+        ) {
+            events::archive_verified(meta, &info.archive_name, hashes);
+        }
     }
 
-    pub fn verify_binary(meta: &Meta, info: &InfoFileImm, binary_name: &str, bytes: &[u8]) {
-        Self::verify_bytes(
+    pub fn verify_binary(
+        meta: &Meta,
+        info: &InfoFileImm,
+        binary_name: &str,
+        hashes: &[(HashType, String)],
+    ) {
+        if Self::verify_hash(
             meta,
             info.bins_hashes
                 .get(binary_name)
                 .unwrap_or_else(|| panic!("{binary_name} is missing hashes.")),
-            &format!("{} {binary_name} binary", &meta.config.target),
-            bytes,
-        );
+            &format!("{} {binary_name} binary", &info.target),
+            hashes,
+        ) {
+            events::bin_verified(meta, binary_name, hashes);
+        }
     }
 
-    fn verify_bytes(meta: &Meta, in_hashes: &Hashes, item: &str, bytes: &[u8]) {
+    /// Panics if the hashes do not match, returning false if hashes are not checked.
+    fn verify_hash(
+        meta: &Meta,
+        in_hashes: &Hashes,
+        item: &str,
+        hashes: &[(HashType, String)],
+    ) -> bool {
         if meta.config.no_hash {
-            return;
+            return false;
         }
 
-        {
-            use sha3::{Digest, Sha3_256, Sha3_512};
+        assert!(
+            !hashes.is_empty(),
+            "{} downloaded {item} for {}@{}, no supported hashes were found.",
+            color!(bright_red, "Could not verify"),
+            meta.id,
+            meta.version
+        );
 
-            // sha3_512
-            if let Some(sha_hash) = in_hashes.get(&HashType::Sha3_512) {
-                let mut hasher = Sha3_512::new();
-                hasher.update(bytes);
-                let hash: Vec<u8> = hasher.finalize().to_vec();
-                let hash = const_hex::encode(hash);
+        if let HashPolicy::Minimum(minimum) = meta.config.hash_policy {
+            let (strongest, _) = hashes[0];
+            assert!(
+                strongest.at_least(minimum),
+                "{} downloaded {item} for {}@{}, it is not hashed with {} or stronger.",
+                color!(bright_red, "Could not verify"),
+                meta.id,
+                meta.version,
+                Into::<&str>::into(minimum)
+            );
+        }
 
-                assert!(
-                    hash.eq(sha_hash),
-                    "sha3_512 hashes do not match for {item}. {sha_hash} != {hash}"
-                );
+        for (hash_type, hash) in hashes {
+            let sha_hash = &in_hashes[hash_type];
+            let hash_type: &str = hash_type.into();
 
-                eprintln!(
-                    "{} {item} for {}@{} with sha3_512.",
-                    color!(bright_white, "Verified"),
-                    meta.id,
-                    meta.version
-                );
-                return;
-            }
+            assert!(
+                hash.eq(sha_hash),
+                "{hash_type} hashes do not match for {item}. {sha_hash} != {hash}"
+            );
 
-            // sha3_256
-            if let Some(sha_hash) = in_hashes.get(&HashType::Sha3_256) {
-                let mut hasher = Sha3_256::new();
-                hasher.update(bytes);
-                let hash: Vec<u8> = hasher.finalize().to_vec();
-                let hash = const_hex::encode(hash);
+            status!(
+                bright_white,
+                "Verified",
+                "{item} for {}@{} with {hash_type}.",
+                meta.id,
+                meta.version
+            );
+        }
 
-                assert!(
-                    hash.eq(sha_hash),
-                    "sha3_256 hashes do not match for {item}. {sha_hash} != {hash}"
-                );
+        true
+    }
 
-                eprintln!(
-                    "{} {item} for {}@{} with sha3_256.",
-                    color!(bright_white, "Verified"),
-                    meta.id,
-                    meta.version
+    /// Checks hashes of an installed file, returning false if any do not match, none were checked,
+    /// or they are weaker than the hash policy allows.
+    pub fn verify_hashes_update(
+        in_hashes: &Hashes,
+        item: &str,
+        hashes: &[(HashType, String)],
+        policy: HashPolicy,
+    ) -> bool {
+        if let (HashPolicy::Minimum(minimum), Some((strongest, _))) = (policy, hashes.first()) {
+            if !strongest.at_least(minimum) {
+                status!(
+                    magenta,
+                    "Update:",
+                    "{item} is not hashed with {} or stronger.",
+                    Into::<&str>::into(minimum)
                 );
-                return;
+                return false;
             }
         }
 
-        {
-            use sha2::{Digest, Sha256, Sha512};
-
-            // sha512
-            if let Some(sha_hash) = in_hashes.get(&HashType::Sha512) {
-                let mut hasher = Sha512::new();
-                hasher.update(bytes);
-                let hash: Vec<u8> = hasher.finalize().to_vec();
-                let hash = const_hex::encode(hash);
-
-                assert!(
-                    hash.eq(sha_hash),
-                    "sha512 hashes do not match for {item}. {sha_hash} != {hash}"
+        for (hash_type, hash) in hashes {
+            let sha_hash = &in_hashes[hash_type];
+            if !hash.eq(sha_hash) {
+                let hash_type: &str = hash_type.into();
+                status!(
+                    magenta,
+                    "Update:",
+                    "{hash_type} hashes do not match for {item}. {sha_hash} != {hash}"
                 );
-
-                eprintln!(
-                    "{} {item} for {}@{} with sha512.",
-                    color!(bright_white, "Verified"),
-                    meta.id,
-                    meta.version
-                );
-                return;
+                return false;
             }
+        }
 
-            // sha256
-            if let Some(sha_hash) = in_hashes.get(&HashType::Sha256) {
-                let mut hasher = Sha256::new();
-                hasher.update(bytes);
-                let hash: Vec<u8> = hasher.finalize().to_vec();
-                let hash = const_hex::encode(hash);
-
-                assert!(
-                    hash.eq(sha_hash),
-                    "sha256 hashes do not match for {item}. {sha_hash} != {hash}"
-                );
+        !hashes.is_empty()
+    }
+}
 
-                eprintln!(
-                    "{} {item} for {}@{} with sha256.",
-                    color!(bright_white, "Verified"),
-                    meta.id,
-                    meta.version
-                );
-                return;
-            }
-        }
+#[cfg(test)]
+mod test {
+    use super::{
+        check_replay, index_dir_name, is_older, trusted_timestamp, Fetcher, TrustedComment,
+    };
+    use crate::data::{HashPolicy, HashType, Hashes};
+
+    #[test]
+    fn test_check_replay() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("index").join("keys.json");
+
+        check_replay(Some(path.clone()), "keys.json", 20);
+        check_replay(Some(path.clone()), "keys.json", 20);
+        check_replay(Some(path.clone()), "keys.json", 30);
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "30");
+
+        let replayed =
+            std::panic::catch_unwind(|| check_replay(Some(path.clone()), "keys.json", 20));
+        assert!(replayed.is_err());
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "30");
+    }
 
-        eprintln!(
-            "Could not verify downloaded {item} for {}@{}.",
-            meta.id, meta.version
+    #[test]
+    fn test_trusted_timestamp() {
+        assert_eq!(
+            trusted_timestamp("timestamp:20\tfile:keys.json", "keys.json"),
+            Some(20)
         );
+        assert_eq!(trusted_timestamp("file:keys.json", "keys.json"), None);
     }
 
-    pub fn verify_bytes_update(in_hashes: &Hashes, item: &str, bytes: &[u8]) -> bool {
-        {
-            use sha3::{Digest, Sha3_256, Sha3_512};
-
-            // sha3_512
-            if let Some(sha_hash) = in_hashes.get(&HashType::Sha3_512) {
-                let mut hasher = Sha3_512::new();
-                hasher.update(bytes);
-                let hash: Vec<u8> = hasher.finalize().to_vec();
-                let hash = const_hex::encode(hash);
-
-                if !hash.eq(sha_hash) {
-                    eprintln!(
-                        "Update: sha3_512 hashes do not match for {item}. {sha_hash} != {hash}"
-                    );
-                    return false;
-                }
-                return true;
-            }
+    #[test]
+    fn test_index_dir_name() {
+        assert_ne!(
+            index_dir_name("gh-pub:github.com/cargo-prebuilt/index"),
+            index_dir_name("gh-pub:github.com/cargo/prebuilt-index")
+        );
+        assert_eq!(
+            index_dir_name("gh-pub:github.com/cargo-prebuilt/index"),
+            index_dir_name("gh-pub:github.com/cargo-prebuilt/index")
+        );
+    }
 
-            // sha3_256
-            if let Some(sha_hash) = in_hashes.get(&HashType::Sha3_256) {
-                let mut hasher = Sha3_256::new();
-                hasher.update(bytes);
-                let hash: Vec<u8> = hasher.finalize().to_vec();
-                let hash = const_hex::encode(hash);
+    #[test]
+    fn test_is_older() {
+        assert!(is_older("0.7.0", "0.7.1"));
+        assert!(is_older("1.0.0-rc.1", "1.0.0"));
+        assert!(!is_older("0.10.0", "0.9.0"));
+        assert!(!is_older("0.7.1", "0.7.1"));
+        assert!(!is_older("nightly", "0.7.1"));
+    }
 
-                if !hash.eq(sha_hash) {
-                    eprintln!(
-                        "Update: sha3_256 hashes do not match for {item}. {sha_hash} != {hash}"
-                    );
-                    return false;
-                }
-                return true;
-            }
-        }
+    #[test]
+    fn test_verify_hashes_update() {
+        let expected = Hashes::from([
+            (HashType::Sha256, "a".to_string()),
+            (HashType::Sha512, "b".to_string()),
+        ]);
+        let sha256 = [(HashType::Sha256, "a".to_string())];
+
+        assert!(Fetcher::verify_hashes_update(
+            &expected,
+            "bin",
+            &sha256,
+            HashPolicy::Strongest
+        ));
+        assert!(!Fetcher::verify_hashes_update(
+            &expected,
+            "bin",
+            &sha256,
+            HashPolicy::Minimum(HashType::Sha512)
+        ));
+        assert!(!Fetcher::verify_hashes_update(
+            &expected,
+            "bin",
+            &[(HashType::Sha256, "c".to_string())],
+            HashPolicy::Strongest
+        ));
+    }
 
-        {
-            use sha2::{Digest, Sha256, Sha512};
-
-            // sha512
-            if let Some(sha_hash) = in_hashes.get(&HashType::Sha512) {
-                let mut hasher = Sha512::new();
-                hasher.update(bytes);
-                let hash: Vec<u8> = hasher.finalize().to_vec();
-                let hash = const_hex::encode(hash);
-
-                if !hash.eq(sha_hash) {
-                    eprintln!(
-                        "Update: sha512 hashes do not match for {item}. {sha_hash} != {hash}"
-                    );
-                    return false;
-                }
-                return true;
+    #[test]
+    fn test_trusted_comment() {
+        assert_eq!(
+            TrustedComment::parse("timestamp:1700000000\tfile:info.json\thashed"),
+            TrustedComment {
+                id: None,
+                version: None,
+                timestamp: Some("1700000000"),
             }
-
-            // sha256
-            if let Some(sha_hash) = in_hashes.get(&HashType::Sha256) {
-                let mut hasher = Sha256::new();
-                hasher.update(bytes);
-                let hash: Vec<u8> = hasher.finalize().to_vec();
-                let hash = const_hex::encode(hash);
-
-                if !hash.eq(sha_hash) {
-                    eprintln!(
-                        "Update: sha256 hashes do not match for {item}. {sha_hash} != {hash}"
-                    );
-                    return false;
-                }
-                return true;
+        );
+        assert_eq!(
+            TrustedComment::parse("id:cargo-prebuilt version:0.7.0 timestamp:1700000000"),
+            TrustedComment {
+                id: Some("cargo-prebuilt"),
+                version: Some("0.7.0"),
+                timestamp: Some("1700000000"),
             }
-        }
-
-        false
+        );
     }
 }
@@ -0,0 +1,227 @@
+use std::{
+    fs::File,
+    io::{self, Read, Write},
+    path::Path,
+};
+
+use sha2::digest::DynDigest;
+
+use crate::data::{HashPolicy, HashType, Hashes};
+
+/// A streaming hasher for any [`HashType`].
+pub type Hasher = Box<dyn DynDigest>;
+
+/// The hasher registry, every [`HashType`] must have a hasher here.
+pub fn hasher(hash_type: HashType) -> Hasher {
+    match hash_type {
+        HashType::Blake3 => Box::new(blake3::Hasher::new()),
+        HashType::Sha3_512 => Box::<sha3::Sha3_512>::default(),
+        HashType::Sha3_256 => Box::<sha3::Sha3_256>::default(),
+        HashType::Sha512 => Box::<sha2::Sha512>::default(),
+        HashType::Sha256 => Box::<sha2::Sha256>::default(),
+    }
+}
+
+/// The sha256 digest of `bytes`, which sigstore signs.
+pub fn sha256(bytes: &[u8]) -> Vec<u8> {
+    let mut hasher = hasher(HashType::Sha256);
+    hasher.update(bytes);
+    hasher.finalize().into_vec()
+}
+
+/// The sha256 digest of everything in `reader`, without reading all of it into memory.
+pub fn sha256_reader(reader: &mut impl Read) -> io::Result<Vec<u8>> {
+    let mut hasher = hasher(HashType::Sha256);
+    let mut buf = [0; 8192];
+    loop {
+        let read = reader.read(&mut buf)?;
+        if read == 0 {
+            return Ok(hasher.finalize().into_vec());
+        }
+        hasher.update(&buf[..read]);
+    }
+}
+
+/// Names of hash types as a CSV list, for printing.
+pub fn names(hash_types: &[HashType]) -> String {
+    hash_types
+        .iter()
+        .map(Into::<&str>::into)
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
+/// Picks which of the expected hash types to check, from most to least preferred.
+pub fn select(expected: &Hashes, policy: HashPolicy) -> Vec<HashType> {
+    let mut hash_types = HashType::PREFERRED
+        .into_iter()
+        .filter(|t| expected.contains_key(t));
+
+    match policy {
+        HashPolicy::All => hash_types.collect(),
+        HashPolicy::Strongest => hash_types.next().into_iter().collect(),
+        // The most preferred hash that is strong enough, or the most preferred to fail on
+        HashPolicy::Minimum(minimum) => {
+            let hash_types: Vec<_> = hash_types.collect();
+            hash_types
+                .iter()
+                .find(|t| t.at_least(minimum))
+                .or_else(|| hash_types.first())
+                .copied()
+                .into_iter()
+                .collect()
+        }
+    }
+}
+
+/// Hashes a file on disk without reading all of it into memory.
+pub fn hash_file(
+    path: &Path,
+    expected: &Hashes,
+    policy: HashPolicy,
+) -> io::Result<Vec<(HashType, String)>> {
+    let mut file = File::open(path)?;
+    let mut writer = HashWriter::new(io::sink(), Some(expected), policy);
+    io::copy(&mut file, &mut writer)?;
+    Ok(writer.finish().1)
+}
+
+/// Writes through to `inner` while hashing with the hash types picked by a [`HashPolicy`].
+pub struct HashWriter<W: Write> {
+    inner: W,
+    hashers: Vec<(HashType, Hasher)>,
+}
+impl<W: Write> HashWriter<W> {
+    /// Passing `None` for `expected` will write without hashing.
+    pub fn new(inner: W, expected: Option<&Hashes>, policy: HashPolicy) -> Self {
+        let hashers = expected
+            .map(|e| select(e, policy))
+            .unwrap_or_default()
+            .into_iter()
+            .map(|hash_type| (hash_type, hasher(hash_type)))
+            .collect();
+
+        Self { inner, hashers }
+    }
+
+    /// Returns the inner writer and the hash types used with their hex encoded hashes.
+    pub fn finish(self) -> (W, Vec<(HashType, String)>) {
+        let hashes = self
+            .hashers
+            .into_iter()
+            .map(|(hash_type, hasher)| (hash_type, const_hex::encode(hasher.finalize())))
+            .collect();
+        (self.inner, hashes)
+    }
+}
+impl<W: Write> Write for HashWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let n = self.inner.write(buf)?;
+        for (_, hasher) in &mut self.hashers {
+            hasher.update(&buf[..n]);
+        }
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{hasher, select, sha256, sha256_reader, HashWriter};
+    use crate::data::{HashPolicy, HashType, Hashes};
+    use std::io::Write;
+
+    #[test]
+    fn test_sha256_reader() {
+        let data = vec![7; 20_000];
+        assert_eq!(sha256_reader(&mut data.as_slice()).unwrap(), sha256(&data));
+    }
+
+    #[test]
+    fn test_select() {
+        let hashes = Hashes::from([
+            (HashType::Sha512, String::new()),
+            (HashType::Sha3_256, String::new()),
+            (HashType::Blake3, String::new()),
+        ]);
+
+        assert_eq!(
+            select(&hashes, HashPolicy::Strongest),
+            vec![HashType::Blake3]
+        );
+        // sha3_256 and blake3 are preferred, but sha512 is the only one strong enough
+        assert_eq!(
+            select(&hashes, HashPolicy::Minimum(HashType::Sha512)),
+            vec![HashType::Sha512]
+        );
+        assert_eq!(
+            select(&hashes, HashPolicy::Minimum(HashType::Sha3_512)),
+            vec![HashType::Blake3]
+        );
+        assert_eq!(select(&hashes, HashPolicy::All).len(), 3);
+    }
+
+    #[test]
+    fn test_registry() {
+        let abc = [
+            (
+                HashType::Sha256,
+                "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad",
+            ),
+            (
+                HashType::Sha3_256,
+                "3a985da74fe225b2045c172d6bd390bd855f086e3e9d525b46bfe24511431532",
+            ),
+            (
+                HashType::Blake3,
+                "6437b3ac38465133ffb63b75273a8db548c558465d79db03fd359c6cd5bd9d85",
+            ),
+        ];
+
+        for (hash_type, expected) in abc {
+            let mut hasher = hasher(hash_type);
+            hasher.update(b"a");
+            hasher.update(b"bc");
+            assert_eq!(const_hex::encode(hasher.finalize()), expected);
+        }
+    }
+
+    #[test]
+    fn test_hash_writer_blake3() {
+        let hashes = Hashes::from([
+            (HashType::Sha256, String::new()),
+            (HashType::Blake3, String::new()),
+            (HashType::Sha3_512, String::new()),
+        ]);
+
+        let mut writer = HashWriter::new(Vec::new(), Some(&hashes), HashPolicy::Strongest);
+        writer.write_all(b"abc").unwrap();
+        let (bytes, hash) = writer.finish();
+
+        assert_eq!(bytes, b"abc");
+        assert_eq!(
+            hash,
+            vec![(
+                HashType::Blake3,
+                "6437b3ac38465133ffb63b75273a8db548c558465d79db03fd359c6cd5bd9d85".to_string()
+            )]
+        );
+    }
+
+    #[test]
+    fn test_hash_writer_all() {
+        let hashes = Hashes::from([
+            (HashType::Sha256, String::new()),
+            (HashType::Sha512, String::new()),
+        ]);
+
+        let writer = HashWriter::new(Vec::new(), Some(&hashes), HashPolicy::All);
+        let (_, hash) = writer.finish();
+
+        let hash_types: Vec<HashType> = hash.into_iter().map(|(t, _)| t).collect();
+        assert_eq!(hash_types, vec![HashType::Sha512, HashType::Sha256]);
+    }
+}
@@ -1,4 +1,7 @@
-use crate::{interact::Interact, BLOB_LIMIT};
+use crate::{
+    interact::{blob_reader, Blob, Interact, NotFound},
+    verbose,
+};
 use serde::{de::DeserializeOwned, Deserialize};
 use std::collections::HashMap;
 use ureq::Agent;
@@ -22,9 +25,10 @@ pub struct GithubPrivate {
     u_repo: String,
     stable_index: Option<Release>,
     index: HashMap<String, Release>,
+    max_blob_size: Option<u64>,
 }
 impl GithubPrivate {
-    pub fn new(agent: Agent, auth_token: String, slug: &str) -> Self {
+    pub fn new(agent: Agent, auth_token: String, slug: &str, max_blob_size: Option<u64>) -> Self {
         let s: Vec<&str> = slug.split('/').collect();
         assert_eq!(s.len(), 3, "Slug '{slug}' is not formatted properly.");
 
@@ -36,10 +40,12 @@ impl GithubPrivate {
             u_repo: s[2].to_string(),
             stable_index: None,
             index: HashMap::new(),
+            max_blob_size,
         }
     }
 
     fn api_call<T: DeserializeOwned>(&self, url: &str) -> anyhow::Result<T> {
+        verbose!(bright_white, "Requesting", "{url}");
         let mut res = self
             .agent
             .get(url)
@@ -58,6 +64,7 @@ impl GithubPrivate {
     }
 
     fn call(&self, url: &str) -> anyhow::Result<String> {
+        verbose!(bright_white, "Requesting", "{url}");
         let mut res = self
             .agent
             .get(url)
@@ -82,7 +89,7 @@ impl GithubPrivate {
             }
         }
 
-        val.ok_or_else(|| anyhow::anyhow!("Could not find {file} in assets list."))
+        val.ok_or_else(|| NotFound(file.to_string()).into())
     }
 
     fn get_release(&mut self, id: &str, version: &str) -> anyhow::Result<Release> {
@@ -102,6 +109,10 @@ impl GithubPrivate {
 }
 impl Interact for GithubPrivate {
     fn get_latest(&mut self, id: &str) -> anyhow::Result<String> {
+        self.get_stable_index(id)
+    }
+
+    fn get_stable_index(&mut self, file_name: &str) -> anyhow::Result<String> {
         if self.stable_index.is_none() {
             self.stable_index = Some(self.api_call(&format!(
                 "{}/repos/{}/{}/releases/tags/stable-index",
@@ -109,13 +120,12 @@ impl Interact for GithubPrivate {
             ))?);
         }
 
-        // Get latest from file
         let si = self
             .stable_index
             .as_ref()
             .expect("Should have stable index!");
 
-        self.get_str_file(&si.assets, id)
+        self.get_str_file(&si.assets, file_name)
     }
 
     fn get_str(&mut self, id: &str, version: &str, file_name: &str) -> anyhow::Result<String> {
@@ -123,13 +133,14 @@ impl Interact for GithubPrivate {
         self.get_str_file(&release.assets, file_name)
     }
 
-    fn get_blob(&mut self, id: &str, version: &str, file_name: &str) -> anyhow::Result<Vec<u8>> {
+    fn get_blob(&mut self, id: &str, version: &str, file_name: &str) -> anyhow::Result<Blob> {
         let release = self.get_release(id, version)?;
 
         let mut val = None;
         for i in &release.assets {
             if i.name.eq(file_name) {
-                let mut res = self
+                verbose!(bright_white, "Requesting", "{}", i.url);
+                let res = self
                     .agent
                     .get(&i.url)
                     .header("Accept", "application/octet-stream")
@@ -139,17 +150,11 @@ impl Interact for GithubPrivate {
                     )
                     .call()?;
 
-                let bytes = res
-                    .body_mut()
-                    .with_config()
-                    .limit(BLOB_LIMIT)
-                    .read_to_vec()?;
-
-                val = Some(bytes);
+                val = Some(blob_reader(res.into_body(), self.max_blob_size));
                 break;
             }
         }
 
-        val.ok_or_else(|| anyhow::anyhow!("Could not find {file_name} in assets list."))
+        val.ok_or_else(|| NotFound(file_name.to_string()).into())
     }
 }
@@ -1,14 +1,22 @@
-use crate::{interact::Interact, BLOB_LIMIT};
+use crate::{
+    interact::{blob_reader, Blob, Interact},
+    verbose,
+};
 use ureq::Agent;
 
 pub struct GithubPublic {
     agent: Agent,
     pre_url: String,
+    max_blob_size: Option<u64>,
 }
 impl GithubPublic {
-    pub fn new(agent: Agent, slug: &str) -> Self {
+    pub fn new(agent: Agent, slug: &str, max_blob_size: Option<u64>) -> Self {
         let pre_url = format!("https://{slug}/releases/download");
-        Self { agent, pre_url }
+        Self {
+            agent,
+            pre_url,
+            max_blob_size,
+        }
     }
 
     fn url(&self, id: &str, version: &str, file: &str) -> String {
@@ -16,6 +24,7 @@ impl GithubPublic {
     }
 
     fn call(&self, url: &str) -> anyhow::Result<String> {
+        verbose!(bright_white, "Requesting", "{url}");
         let mut res = self.agent.get(url).call()?;
         let s = res.body_mut().read_to_string()?;
         Ok(s.trim().to_string())
@@ -27,21 +36,21 @@ impl Interact for GithubPublic {
         self.call(&url)
     }
 
+    fn get_stable_index(&mut self, file_name: &str) -> anyhow::Result<String> {
+        let url = format!("{}/stable-index/{file_name}", self.pre_url);
+        self.call(&url)
+    }
+
     fn get_str(&mut self, id: &str, version: &str, file_name: &str) -> anyhow::Result<String> {
         let url = self.url(id, version, file_name);
         self.call(&url)
     }
 
-    fn get_blob(&mut self, id: &str, version: &str, file_name: &str) -> anyhow::Result<Vec<u8>> {
+    fn get_blob(&mut self, id: &str, version: &str, file_name: &str) -> anyhow::Result<Blob> {
         let url = self.url(id, version, file_name);
 
-        let mut res = self.agent.get(&url).call()?;
-        let bytes = res
-            .body_mut()
-            .with_config()
-            .limit(BLOB_LIMIT)
-            .read_to_vec()?;
-
-        Ok(bytes)
+        verbose!(bright_white, "Requesting", "{url}");
+        let res = self.agent.get(&url).call()?;
+        Ok(blob_reader(res.into_body(), self.max_blob_size))
     }
 }
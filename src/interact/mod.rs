@@ -1,13 +1,20 @@
+use core::fmt;
+use std::io::Read;
 use ureq::Agent;
 
-use crate::color;
+use crate::status;
 
 #[cfg(feature = "github-private")]
 mod github_private;
 #[cfg(feature = "github-public")]
 mod github_public;
 
-pub fn create_interactive(input: &str, auth: Option<&String>, agent: Agent) -> Box<dyn Interact> {
+pub fn create_interactive(
+    input: &str,
+    auth: Option<&String>,
+    max_blob_size: Option<u64>,
+    agent: Agent,
+) -> Box<dyn Interact> {
     // Github public
     if input.starts_with("gh-pub:") {
         #[cfg(feature = "github-public")]
@@ -15,8 +22,8 @@ pub fn create_interactive(input: &str, auth: Option<&String>, agent: Agent) -> B
             let url = input
                 .get(7..input.len())
                 .expect("Missing url after gh-pub:");
-            eprintln!("{} index https://{url}", color!(bright_cyan, "Using"));
-            return Box::new(github_public::GithubPublic::new(agent, url));
+            status!(bright_cyan, "Using", "index https://{url}");
+            return Box::new(github_public::GithubPublic::new(agent, url, max_blob_size));
         }
         #[cfg(not(feature = "github-public"))]
         panic!("Using this index ({input}) requires the github-public feature!");
@@ -29,11 +36,12 @@ pub fn create_interactive(input: &str, auth: Option<&String>, agent: Agent) -> B
             let url = input
                 .get(7..input.len())
                 .expect("Missing url after gh-pri:");
-            eprintln!("{} index https://{url}", color!(bright_cyan, "Using"));
+            status!(bright_cyan, "Using", "index https://{url}");
             return Box::new(github_private::GithubPrivate::new(
                 agent,
                 auth.expect("Need auth token for private index.").clone(),
                 url,
+                max_blob_size,
             ));
         }
         #[cfg(not(feature = "github-private"))]
@@ -43,8 +51,42 @@ pub fn create_interactive(input: &str, auth: Option<&String>, agent: Agent) -> B
     panic!("This index ({input}) is not supported or malformed.");
 }
 
+/// A file that does not exist in the index.
+#[derive(Debug)]
+pub struct NotFound(pub String);
+impl fmt::Display for NotFound {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Could not find {} in the index.", self.0)
+    }
+}
+impl std::error::Error for NotFound {}
+
+/// If an error means the file does not exist, instead of failing to fetch it.
+pub fn is_not_found(err: &anyhow::Error) -> bool {
+    err.downcast_ref::<NotFound>().is_some()
+        || matches!(
+            err.downcast_ref::<ureq::Error>(),
+            Some(ureq::Error::StatusCode(404))
+        )
+}
+
+/// A downloaded blob and its length, if the server sent one.
+pub type Blob = (Box<dyn Read>, Option<u64>);
+
+/// Reader over a downloaded blob, erroring once it goes over `max_blob_size` bytes.
+#[cfg(any(feature = "github-public", feature = "github-private"))]
+fn blob_reader(body: ureq::Body, max_blob_size: Option<u64>) -> Blob {
+    let len = body.content_length();
+    let reader: Box<dyn Read> = match max_blob_size {
+        Some(limit) => Box::new(body.into_with_config().limit(limit).reader()),
+        None => Box::new(body.into_reader()),
+    };
+    (reader, len)
+}
+
 pub trait Interact {
     fn get_latest(&mut self, id: &str) -> anyhow::Result<String>;
+    fn get_stable_index(&mut self, file_name: &str) -> anyhow::Result<String>;
     fn get_str(&mut self, id: &str, version: &str, file_name: &str) -> anyhow::Result<String>;
-    fn get_blob(&mut self, id: &str, version: &str, file_name: &str) -> anyhow::Result<Vec<u8>>;
+    fn get_blob(&mut self, id: &str, version: &str, file_name: &str) -> anyhow::Result<Blob>;
 }
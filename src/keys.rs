@@ -0,0 +1,407 @@
+use std::{
+    collections::HashSet,
+    fs::{self, create_dir_all},
+    io::{self, IsTerminal},
+    path::{Path, PathBuf},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use minisign_verify::{PublicKey, Signature};
+
+use crate::{
+    color, coloring,
+    config::{self, Config},
+    data::{usable_keys, HashType, KeysFile, KeysFileV1, PubKey},
+    get, hashing,
+    interact::{self, Interact},
+    status, warn,
+};
+
+/// Id of keys pinned on first use, so changes to the key published by the index are noticed.
+const TOFU_ID: &str = "tofu";
+/// Public key published by an index in its stable index.
+const INDEX_KEY_FILE: &str = "index.pub";
+
+/// Today's date in UTC as YYYY-MM-DD.
+pub fn today() -> String {
+    let days = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .expect("System time is before the unix epoch.")
+        .as_secs()
+        / 86_400;
+
+    let (year, month, day) = civil_from_days(days);
+    format!("{year:04}-{month:02}-{day:02}")
+}
+
+/// Converts days since the unix epoch to a (year, month, day) date.
+/// <https://howardhinnant.github.io/date_algorithms.html#civil_from_days>
+const fn civil_from_days(days: u64) -> (u64, u64, u64) {
+    let z = days + 719_468;
+    let era = z / 146_097;
+    let doe = z - era * 146_097;
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+    (year, month, day)
+}
+
+/// Most numbered signatures fetched for a file.
+const MAX_SIGS: usize = 32;
+
+/// Fetches `sig_file`, and when more than one signature is needed also `sig_file.1`, `sig_file.2`, ...
+/// until one is missing. Errors other than a missing signature fail.
+pub fn fetch_sigs(
+    threshold: usize,
+    sig_file: &str,
+    mut fetch: impl FnMut(&str) -> anyhow::Result<String>,
+) -> Vec<String> {
+    // Only a missing signature ends the list, any other error fails
+    let mut fetch = |f: &str| match fetch(f) {
+        Ok(sig) => Some(sig),
+        Err(err) if interact::is_not_found(&err) => None,
+        Err(err) => panic!("{} fetch {f}. {err}", color!(bright_red, "Could not")),
+    };
+
+    let mut sigs: Vec<String> = fetch(sig_file).into_iter().collect();
+    if threshold > 1 {
+        sigs.extend((1..=MAX_SIGS).map_while(|n| fetch(&format!("{sig_file}.{n}"))));
+    }
+    sigs
+}
+
+/// Verifies minisign signatures with the keys, returning how many distinct keys signed
+/// along with the signatures that verified.
+pub fn verify_minisigs(
+    keys: &[&PubKey],
+    sigs: &[String],
+    raw_file: &str,
+) -> (usize, Vec<Signature>) {
+    let mut signers = HashSet::new();
+    let mut verified = Vec::new();
+
+    for sig in sigs {
+        let signature = Signature::decode(sig).expect("Signature was malformed.");
+        let signer = keys.iter().find(|k| {
+            let pk = PublicKey::from_base64(&k.key).expect("Public key was malformed.");
+            pk.verify(raw_file.as_bytes(), &signature, false).is_ok()
+        });
+
+        if let Some(signer) = signer {
+            signers.insert(signer.key.as_str());
+            verified.push(signature);
+        }
+    }
+
+    (signers.len(), verified)
+}
+
+/// Keys from the config, previously verified key rotations, and the key rotation file
+/// published by the index (stable-index/keys.json), if there is one.
+pub fn load(config: &Config, interact: &mut dyn Interact) -> Vec<PubKey> {
+    let mut keys = config.pub_keys.clone();
+
+    let path = keys_path(config, "keys.json");
+    // Older versions only appended keys, so saved keys are merged with themselves first
+    let saved = merge_keys(Vec::new(), read_keys(config, path.as_ref())).0;
+    keys.extend(saved.iter().cloned());
+
+    if usable_keys(&keys, &today()).is_empty() {
+        keys.push(trust_on_first_use(config, interact));
+    } else {
+        check_pinned(config, &keys, interact);
+    }
+
+    let raw_keys_file = match interact.get_stable_index("keys.json") {
+        Ok(raw) => raw,
+        // Indexes do not have to publish a key rotation file
+        Err(err) if interact::is_not_found(&err) => return keys,
+        Err(err) => panic!(
+            "{} fetch keys.json for index {}. {err}",
+            color!(bright_red, "Could not"),
+            config.index
+        ),
+    };
+    let sigs = fetch_sigs(config.sig_threshold, "keys.json.minisig", |f| {
+        interact.get_stable_index(f)
+    });
+
+    let (signers, signatures) =
+        verify_minisigs(&usable_keys(&keys, &today()), &sigs, &raw_keys_file);
+    assert!(
+        signers >= config.sig_threshold,
+        "{} keys.json for index {}, it is signed by {signers} of {} required trusted keys.",
+        color!(bright_red, "Could not verify"),
+        config.index,
+        config.sig_threshold
+    );
+
+    // An older keys.json could undo a revocation or a shortened validity
+    let what = format!("keys.json for index {}", config.index);
+    let newest = signatures
+        .iter()
+        .filter_map(|s| get::trusted_timestamp(s.trusted_comment(), &what))
+        .max();
+    if let Some(timestamp) = newest {
+        let path = get::index_data_path(config, "timestamps").map(|mut path| {
+            path.push("keys.json");
+            path
+        });
+        get::check_replay(path, &what, timestamp);
+    }
+    status!(
+        bright_white,
+        "Verified",
+        "keys.json for index {} with minisign.",
+        config.index
+    );
+
+    let keys_file: KeysFile = serde_json::from_str(&raw_keys_file)
+        .unwrap_or_else(|_| panic!("keys.json is malformed for index {}", config.index));
+    let keys_file: KeysFileV1 = keys_file.into();
+
+    // Saved keys come right after the keys from the config
+    let saved_range = config.pub_keys.len()..config.pub_keys.len() + saved.len();
+    let (saved, changed) = merge_keys(saved, keys_file.keys);
+    keys.splice(saved_range, saved.iter().cloned());
+
+    if let (true, Some(path)) = (changed, path) {
+        write_keys(config, &path, &saved);
+    }
+
+    keys
+}
+
+/// Where keys for the index of the config are kept.
+fn keys_path(config: &Config, file: &str) -> Option<PathBuf> {
+    get::index_data_path(config, "keys").map(|mut path| {
+        path.push(file);
+        path
+    })
+}
+
+fn read_keys(config: &Config, path: Option<&PathBuf>) -> Vec<PubKey> {
+    path.and_then(|path| fs::read_to_string(path).ok())
+        .map(|s| {
+            serde_json::from_str(&s).unwrap_or_else(|_| {
+                panic!(
+                    "Saved keys for index {} are malformed, remove them or fix them.",
+                    config.index
+                )
+            })
+        })
+        .unwrap_or_default()
+}
+
+/// Returns if the keys were written.
+fn write_keys(config: &Config, path: &Path, keys: &[PubKey]) -> bool {
+    let json = serde_json::to_string_pretty(keys).expect("Could not serialize keys.");
+    if path
+        .parent()
+        .map_or(true, |dir| create_dir_all(dir).is_err())
+        || fs::write(path, json).is_err()
+    {
+        warn!(
+            "Could not",
+            "save keys for index {} at '{}'.",
+            config.index,
+            path.display()
+        );
+        return false;
+    }
+    true
+}
+
+/// Merges keys by their key, so the metadata from the newest key rotation file is used.
+/// Keys are never dropped and a revocation is never undone by a later file.
+/// Returns if anything changed.
+fn merge_keys(mut saved: Vec<PubKey>, newer: Vec<PubKey>) -> (Vec<PubKey>, bool) {
+    let mut changed = false;
+    for mut key in newer {
+        if let Some(old) = saved.iter_mut().find(|k| k.key.eq(&key.key)) {
+            key.revoked |= old.revoked;
+            if key.ne(old) {
+                *old = key;
+                changed = true;
+            }
+        } else {
+            saved.push(key);
+            changed = true;
+        }
+    }
+    (saved, changed)
+}
+
+/// The last line of the key file published by the index, skipping its comment.
+fn published_key(interact: &mut dyn Interact) -> Option<String> {
+    let raw = interact.get_stable_index(INDEX_KEY_FILE).ok()?;
+    raw.lines()
+        .map(str::trim)
+        .rfind(|l| !l.is_empty() && !l.starts_with("untrusted comment:"))
+        .map(ToString::to_string)
+}
+
+/// sha256 of the base64 encoded key, which can be checked with `printf %s $KEY | sha256sum`.
+fn fingerprint(key: &str) -> String {
+    let mut hasher = hashing::hasher(HashType::Sha256);
+    hasher.update(key.as_bytes());
+    const_hex::encode(hasher.finalize())
+}
+
+/// Asks to trust the key published by an index that has no usable keys,
+/// then pins it in the config file.
+fn trust_on_first_use(config: &Config, interact: &mut dyn Interact) -> PubKey {
+    let no_keys = || {
+        format!(
+            "{} for index '{}'. Please add one with --pub-key or use --no-sig.",
+            color!(bright_red, "No usable public key(s)"),
+            config.index
+        )
+    };
+
+    // The prompt is not a log record, so it is never shown with --format json
+    assert!(
+        !config.ci && !coloring::is_json() && io::stdin().is_terminal(),
+        "{}",
+        no_keys()
+    );
+    let Some(key) = published_key(interact) else {
+        panic!("{}", no_keys());
+    };
+    PublicKey::from_base64(&key).expect("Public key published by the index was malformed.");
+
+    // A warning, so the key being trusted is shown even with -q
+    warn!("Untrusted", "index {} has no trusted public keys, but publishes the key {key}\nFingerprint (sha256): {}", config.index,
+        fingerprint(&key));
+    eprint!("Trust this key for the index and save it? [y/N] ");
+
+    let mut answer = String::new();
+    io::stdin()
+        .read_line(&mut answer)
+        .expect("Could not read answer.");
+    assert!(matches!(answer.trim(), "y" | "Y" | "yes"), "{}", no_keys());
+
+    let key = PubKey {
+        id: Some(TOFU_ID.to_string()),
+        key,
+        valid_from: None,
+        valid_until: None,
+        revoked: false,
+    };
+    config::pin_key(config, &key);
+    key
+}
+
+/// Warns if the key published by an index is not one that was pinned on first use.
+fn check_pinned(config: &Config, keys: &[PubKey], interact: &mut dyn Interact) {
+    let mut pinned = keys.iter().filter(|k| k.id.as_deref() == Some(TOFU_ID));
+    let Some(first) = pinned.next() else {
+        return;
+    };
+
+    let published = published_key(interact);
+    if published
+        .as_ref()
+        .is_some_and(|p| p.eq(&first.key) || pinned.any(|k| k.key.eq(p)))
+    {
+        return;
+    }
+
+    warn!(
+        "WARNING!",
+        "The public key published by index {} has {} since it was trusted!\n\
+        Someone may be tampering with the index. If the index rotated its key on purpose,\n\
+        check the new key out of band and update the keys of the index in '{}'.",
+        config.index,
+        published.map_or_else(
+            || "been removed".to_string(),
+            |p| format!("changed to {p} (Fingerprint: {})", fingerprint(&p))
+        ),
+        config.conf_file.as_ref().map_or_else(
+            || "the config file".to_string(),
+            |p| p.display().to_string()
+        )
+    );
+}
+
+#[cfg(test)]
+mod test {
+    use super::{civil_from_days, fetch_sigs, fingerprint, merge_keys, verify_minisigs};
+    use crate::{data::PubKey, interact::NotFound};
+
+    #[test]
+    fn test_verify_minisigs() {
+        let data = include_str!("../test/pubdata.test");
+        let sig = include_str!("../test/pubdata.test.minisig").to_string();
+        let key = PubKey::from(include_str!("../test/pubdata.pub").trim().to_string());
+
+        // The same key signing twice only counts once
+        let (signers, signatures) = verify_minisigs(&[&key], &[sig.clone(), sig], data);
+        assert_eq!(signers, 1);
+        assert_eq!(signatures.len(), 2);
+
+        let (signers, _) = verify_minisigs(&[], &[], data);
+        assert_eq!(signers, 0);
+    }
+
+    #[test]
+    fn test_merge_keys() {
+        let key = |valid_until: &str, revoked: bool| PubKey {
+            id: None,
+            key: "KEY".to_string(),
+            valid_from: None,
+            valid_until: Some(valid_until.to_string()),
+            revoked,
+        };
+
+        // A shortened validity replaces the saved one
+        let (saved, changed) = merge_keys(
+            vec![key("2030-01-01", false)],
+            vec![key("2026-01-01", false)],
+        );
+        assert!(changed);
+        assert_eq!(saved, vec![key("2026-01-01", false)]);
+
+        // A revocation is kept
+        let (saved, _) = merge_keys(
+            vec![key("2030-01-01", true)],
+            vec![key("2030-01-01", false)],
+        );
+        assert_eq!(saved, vec![key("2030-01-01", true)]);
+
+        let (saved, changed) = merge_keys(saved, vec![key("2030-01-01", false)]);
+        assert!(!changed);
+        assert_eq!(saved.len(), 1);
+    }
+
+    #[test]
+    fn test_fingerprint() {
+        assert_eq!(
+            fingerprint("RWT+Z7r4TSAsutDuNpKn2uvyHHzG1vI4P8uLoBaS8yG8HYnGHznJjmQE"),
+            "a7558696337a0ace36ee019dd19003b7b04c01c4f8c6eaf16532512f582ef914"
+        );
+    }
+
+    #[test]
+    fn test_fetch_sigs() {
+        let fetch = |f: &str| match f {
+            "info.json.minisig" | "info.json.minisig.1" | "info.json.minisig.2" => {
+                Ok(f.to_string())
+            }
+            _ => Err(NotFound(f.to_string()).into()),
+        };
+
+        assert_eq!(fetch_sigs(1, "info.json.minisig", fetch).len(), 1);
+        assert_eq!(fetch_sigs(2, "info.json.minisig", fetch).len(), 3);
+    }
+
+    #[test]
+    fn test_civil_from_days() {
+        assert_eq!(civil_from_days(0), (1970, 1, 1));
+        assert_eq!(civil_from_days(11_016), (2000, 2, 29));
+        assert_eq!(civil_from_days(20_745), (2026, 10, 19));
+    }
+}
@@ -10,21 +10,27 @@
 // TODO: Improve errors? Make them more readable.
 
 mod coloring;
+mod completions;
 mod config;
 mod data;
 mod events;
+mod extract;
 mod get;
+mod hashing;
 mod interact;
+mod keys;
+mod progress;
+#[cfg(feature = "sigstore")]
+mod sigstore;
+mod target;
 
-use flate2::read::GzDecoder;
 use std::{
-    fs::{self, create_dir_all, File},
-    io::{Read, Write},
+    fs::{self, create_dir_all},
     path::Path,
     str,
     sync::Arc,
+    time::Instant,
 };
-use tar::Archive;
 use ureq::config::AutoHeaderValue;
 
 use crate::{
@@ -69,24 +75,17 @@ static DEFAULT_TARGET: &str = match option_env!("PREBUILT_BUILD_DEFAULT_TARGET")
     Some(s) => s,
     None => env!("TARGET"),
 };
+static BUILD_TARGET: &str = env!("TARGET");
 
-const BLOB_LIMIT: u64 = 1_048_576 * 50; // 50 MB
-
+#[allow(clippy::too_many_lines)]
 fn main() {
-    #[cfg(debug_assertions)]
-    dbg!(
-        BUILD_REPO_LINK,
-        BUILD_ISSUES_LINK,
-        BUILD_DOCS_LINK,
-        DEFAULT_INDEX,
-        DEFAULT_INDEX_KEY,
-        DEFAULT_TARGET
-    );
+    let start = Instant::now();
 
     for a in std::env::args_os() {
         if a.eq("--version") || a.eq("-V") {
             println!("Version: {}", env!("CARGO_PKG_VERSION"));
             println!("Default Target: {DEFAULT_TARGET}");
+            println!("Build Target: {BUILD_TARGET}");
             println!("Default Index: {DEFAULT_INDEX}");
             println!("Default Index Key(s): {DEFAULT_INDEX_KEY}");
             std::process::exit(0);
@@ -100,8 +99,16 @@ fn main() {
 
     let config = config::get();
     let config = &config;
-    #[cfg(debug_assertions)]
-    dbg!(&config);
+    if config.out {
+        events::set_sink(config);
+        events::error_hook(config.event_version);
+    }
+    debug!(
+        bright_white,
+        "Build",
+        "{BUILD_TARGET} default target {DEFAULT_TARGET}, index {DEFAULT_INDEX} with key(s) {DEFAULT_INDEX_KEY}"
+    );
+    debug!(bright_white, "Config", "{:#?}", config.redacted());
 
     // Check if a needed feature was excluded.
     should_error();
@@ -137,8 +144,11 @@ fn main() {
     let mut fetcher = Fetcher::new(config, agent);
 
     // Get pkgs
-    for pkg in &config.packages {
+    for (i, pkg) in config.packages.iter().enumerate() {
+        progress::package(i, config.packages.len());
+
         let mut id = pkg.as_str();
+        #[allow(clippy::useless_let_if_seq)]
         let mut version = None; // None will pull the latest version
 
         // If there is a version string get it
@@ -149,48 +159,91 @@ fn main() {
 
         // If --get-latest then get latest version and print out latest event
         if config.get_latest {
-            events::get_latest(id, &fetcher.get_latest(id));
+            events::get_latest(config.event_version, id, &fetcher.get_latest(id, config));
             continue;
         }
 
         // Get the version that fetcher is using
-        let version = version.map_or_else(|| fetcher.get_latest(id), ToString::to_string);
+        let version = version.map_or_else(|| fetcher.get_latest(id, config), ToString::to_string);
         let version = &version;
+        let started = Instant::now();
 
         let meta = Meta::new(id, version, config);
         let meta = &meta;
-
-        events::target(meta);
+        events::current(id, version);
+        events::configured_target(meta);
 
         // Download and hash tar
-        let info = fetcher.download_info(meta);
+        let info = fetcher
+            .download_info(meta)
+            .unwrap_or_else(|built_for| unsupported_target(meta, &built_for));
         let info = &info;
 
+        events::target(meta, &info.target);
+
+        // Remove instead of install
+        if config.uninstall {
+            uninstall(meta, info);
+
+            status!(bright_green, "Uninstalled", "{id}@{version}.");
+            events::uninstalled(meta, info);
+            continue;
+        }
+
         // Check to update or not
         if config.update && !should_update(meta, info) {
-            eprintln!(
-                "{} for {id}@{version}. Already up to date.",
-                color!(magenta, "No Change")
+            status!(
+                magenta,
+                "No Change",
+                "for {id}@{version}. Already up to date."
             );
             events::no_update(meta);
             continue;
         }
 
-        let tar_bytes = fetcher.download_blob(meta, info);
+        let archive = fetcher.download_blob(meta, info);
 
-        // Extract Tar
-        extract(meta, info, tar_bytes);
+        // Extract archive
+        extract::extract(meta, info, archive);
 
         // Reports
         if !config.ci {
             fetcher.reports(meta, info);
         }
 
-        eprintln!("{} {id}@{version}.", color!(bright_green, "Installed"));
-        events::installed(meta);
+        status!(bright_green, "Installed", "{id}@{version}.");
+        verbose!(
+            bright_white,
+            "Took",
+            "{:.2?} for {id}@{version}.",
+            started.elapsed()
+        );
+        events::installed(meta, info);
     }
 
-    eprintln!("{}", color!(green, "Done!"));
+    status!(green, "Done!");
+    verbose!(bright_white, "Took", "{:.2?} in total.", start.elapsed());
+}
+
+/// Fails with the targets that were built for, and a suggestion for likely typos.
+fn unsupported_target(meta: &Meta, built_for: &[String]) -> ! {
+    let built_for: Vec<&str> = built_for.iter().map(String::as_str).collect();
+    let suggestion = meta
+        .config
+        .targets
+        .iter()
+        .find_map(|t| target::suggest(t, &built_for))
+        .map(|s| format!(" Did you mean {}?", color!(bright_white, s)))
+        .unwrap_or_default();
+
+    panic!(
+        "{}@{} does {} target {}.{suggestion}\nAvailable targets: {}",
+        meta.id,
+        meta.version,
+        color!(bright_red, "not support"),
+        meta.config.targets.join(", "),
+        built_for.join(", ")
+    );
 }
 
 fn should_update(meta: &Meta, info: &InfoFileImm) -> bool {
@@ -199,45 +252,49 @@ fn should_update(meta: &Meta, info: &InfoFileImm) -> bool {
     for bin in &info.bins {
         if let Some(hashes) = info.bins_hashes.get(bin) {
             let mut bin_name = bin.clone();
-            if meta.config.target.contains("windows") {
+            if info.target.contains("windows") {
                 bin_name.push_str(".exe");
             }
 
             let mut path = meta.config.path.clone();
             path.push(&bin_name);
 
-            if let Ok(bytes) = fs::read(&path) {
-                if Fetcher::verify_bytes_update(hashes, bin, &bytes) {
+            let policy = meta.config.hash_policy;
+            if let Ok(file_hashes) = hashing::hash_file(&path, hashes, policy) {
+                if Fetcher::verify_hashes_update(hashes, bin, &file_hashes, policy) {
                     should_update = false;
                     continue;
                 }
 
-                eprintln!(
-                    "{} for {}@{}. Hashes do not match.",
-                    color!(magenta, "Will Update"),
+                status!(
+                    magenta,
+                    "Will Update",
+                    "for {}@{}. Hashes do not match.",
                     meta.id,
-                    meta.version,
+                    meta.version
                 );
                 should_update = true;
                 break;
             }
 
-            eprintln!(
-                "{} for {}@{}. Cannot find/open binary '{}'.",
-                color!(magenta, "Will Update"),
+            status!(
+                magenta,
+                "Will Update",
+                "for {}@{}. Cannot find/open binary '{}'.",
                 meta.id,
                 meta.version,
-                bin_name,
+                bin_name
             );
             should_update = true;
             break;
         }
 
-        eprintln!(
-            "{} for {}@{}. Missing binary hash.",
-            color!(magenta, "Will Update"),
+        status!(
+            magenta,
+            "Will Update",
+            "for {}@{}. Missing binary hash.",
             meta.id,
-            meta.version,
+            meta.version
         );
         should_update = true;
         break;
@@ -246,81 +303,48 @@ fn should_update(meta: &Meta, info: &InfoFileImm) -> bool {
     should_update
 }
 
-fn extract(meta: &Meta, info: &InfoFileImm, tar_bytes: Vec<u8>) {
-    let reader = std::io::Cursor::new(tar_bytes);
-    let mut archive = Archive::new(GzDecoder::new(reader));
-
-    let es = archive
-        .entries()
-        .expect("Cannot get entries from downloaded tar.");
+fn uninstall(meta: &Meta, info: &InfoFileImm) {
+    let mut paths = Vec::new();
 
-    eprintln!(
-        "{} {}@{}...",
-        color!(bright_blue, "Extracting"),
-        meta.id,
-        meta.version
-    );
+    for bin in &info.bins {
+        let mut bin_name = bin.clone();
+        if info.target.contains("windows") {
+            bin_name.push_str(".exe");
+        }
 
-    for e in es {
-        let mut e = e.expect("Malformed entry in tarball.");
+        let mut path = meta.config.path.clone();
+        path.push(&bin_name);
+        paths.push(path);
+    }
 
-        let bin_path = e.path().expect("Could not extract path from archive.");
-        let str_name = bin_path
-            .clone()
-            .into_owned()
-            .into_os_string()
-            .into_string()
-            .expect("Archive has non utf-8 path.");
+    // Files in extra directories are only known from the archive, so they are left alone.
+    paths.extend(
+        info.extras
+            .keys()
+            .filter(|e| !e.ends_with('/'))
+            .filter_map(|e| extract::extra_path(meta, info, e)),
+    );
+    // Completions are only installed with --completions, so others with the same name are kept
+    if meta.config.completions {
+        paths.extend(completions::paths(info));
+    }
 
-        // Make sure there are no path separators since this will be appended
-        assert!(
-            !str_name.contains(std::path::is_separator),
-            "{} path separator in archive for {}@{}",
-            color!(bright_red, "Illegal"),
-            meta.id,
-            meta.version
-        );
+    for path in paths {
+        if !path.exists() {
+            continue;
+        }
 
         assert!(
-            Fetcher::is_bin(info, &str_name),
-            "{} binary ({str_name}) in archive for {}@{}",
-            color!(bright_red, "Illegal"),
+            fs::remove_file(&path).is_ok(),
+            "{} remove '{}' for {}@{}.",
+            color!(bright_red, "Could not"),
+            path.display(),
             meta.id,
             meta.version
         );
 
-        let mut path = meta.config.path.clone();
-        path.push(bin_path);
-
-        let mut blob_data = Vec::new();
-        e.read_to_end(&mut blob_data)
-            .expect("Could not extract binary from archive.");
-
-        if meta.config.hash_bins {
-            Fetcher::verify_binary(meta, info, &str_name, &blob_data);
-        }
-
-        let mut file = File::create(&path).expect("Could not open file to write binary to.");
-        file.write_all(&blob_data)
-            .expect("Could not write binary to file.");
-
-        // Attempt to add +x permission on unix platforms.
-        #[cfg(target_family = "unix")]
-        {
-            use std::os::unix::fs::PermissionsExt;
-            if fs::set_permissions(&path, fs::Permissions::from_mode(0o755)).is_err() {
-                eprintln!(
-                    "Could not set mode 755 for {}@{} binary {str_name}",
-                    meta.id, meta.version
-                );
-            }
-        }
-
-        let abs = dunce::canonicalize(path).expect("Could not canonicalize install path.");
-
-        eprintln!("{} {}", color!(bright_purple, "Installed"), abs.display());
-
-        events::binary_installed(meta, &abs.display().to_string());
+        status!(bright_purple, "Removed", "{}", path.display());
+        events::removed(meta, &path.display().to_string());
     }
 }
 
@@ -0,0 +1,202 @@
+// Byte counts are converted to floats for display only, so losing precision is fine.
+#![allow(
+    clippy::cast_precision_loss,
+    clippy::cast_possible_truncation,
+    clippy::cast_sign_loss
+)]
+
+use core::{
+    sync::atomic::{AtomicUsize, Ordering},
+    time::Duration,
+};
+use std::{
+    io::{self, IsTerminal, Write},
+    time::Instant,
+};
+
+use crate::{
+    coloring::{self, Verbosity},
+    config::Config,
+};
+
+/// Width of the bar between the brackets.
+const WIDTH: usize = 24;
+/// Minimum time between redraws.
+const REDRAW: Duration = Duration::from_millis(100);
+
+/// Which package is being worked on and how many there are, for the aggregate bar.
+static PACKAGE: AtomicUsize = AtomicUsize::new(0);
+static PACKAGES: AtomicUsize = AtomicUsize::new(0);
+
+/// Marks which package (starting at 0) out of `total` is being worked on.
+pub fn package(index: usize, total: usize) {
+    PACKAGE.store(index, Ordering::Relaxed);
+    PACKAGES.store(total, Ordering::Relaxed);
+}
+
+/// Progress bars are only drawn for people watching a terminal, and not with `-q`.
+fn enabled(config: &Config) -> bool {
+    !config.ci
+        && !coloring::is_json()
+        && coloring::verbosity() > Verbosity::Quiet
+        && io::stderr().is_terminal()
+}
+
+/// A progress bar for one download, with an aggregate bar under it when installing multiple packages.
+pub struct Bar {
+    name: String,
+    total: Option<u64>,
+    bytes: u64,
+    start: Instant,
+    drawn: Option<Instant>,
+}
+impl Bar {
+    /// Returns None when progress bars should not be drawn.
+    pub fn new(config: &Config, name: &str, total: Option<u64>) -> Option<Self> {
+        enabled(config).then(|| Self {
+            name: name.to_string(),
+            total: total.filter(|t| *t > 0),
+            bytes: 0,
+            start: Instant::now(),
+            drawn: None,
+        })
+    }
+
+    pub fn set(&mut self, bytes: u64) {
+        self.bytes = bytes;
+        if self.drawn.map_or(true, |d| d.elapsed() >= REDRAW) {
+            self.draw();
+            self.drawn = Some(Instant::now());
+        }
+    }
+
+    fn draw(&self) {
+        let elapsed = self.start.elapsed().as_secs_f64();
+        let rate = if elapsed > 0.0 {
+            self.bytes as f64 / elapsed
+        } else {
+            0.0
+        };
+
+        // Without a length from the server only the bytes so far and rate are known
+        let line = self.total.map_or_else(
+            || {
+                format!(
+                    "{} {} {}/s",
+                    self.name,
+                    human_bytes(self.bytes as f64),
+                    human_bytes(rate)
+                )
+            },
+            |total| {
+                let eta = if rate > 0.0 {
+                    let left = total.saturating_sub(self.bytes) as f64 / rate;
+                    format!(" ETA {}", human_duration(left))
+                } else {
+                    String::new()
+                };
+                format!(
+                    "{} {} {}/{} {}/s{eta}",
+                    self.name,
+                    bar(fraction(self.bytes, total)),
+                    human_bytes(self.bytes as f64),
+                    human_bytes(total as f64),
+                    human_bytes(rate)
+                )
+            },
+        );
+
+        let mut stderr = io::stderr().lock();
+        let _ = write!(stderr, "\r\x1b[2K{line}");
+        if let Some(total) = self.aggregate() {
+            let _ = write!(stderr, "\n\x1b[2K{total}\x1b[1A");
+        }
+        let _ = stderr.flush();
+    }
+
+    /// The bar over all packages, counting the current download as partly done.
+    fn aggregate(&self) -> Option<String> {
+        let packages = PACKAGES.load(Ordering::Relaxed);
+        if packages <= 1 {
+            return None;
+        }
+        let package = PACKAGE.load(Ordering::Relaxed);
+
+        let current = self.total.map_or(0.0, |t| fraction(self.bytes, t));
+        let done = (package as f64 + current) / packages as f64;
+        Some(format!("Total {} {}/{packages}", bar(done), package + 1))
+    }
+
+    /// Clears the bars, so status lines are printed in their place.
+    pub fn finish(&self) {
+        if self.drawn.is_none() {
+            return;
+        }
+
+        let mut stderr = io::stderr().lock();
+        let _ = write!(stderr, "\r\x1b[2K");
+        if PACKAGES.load(Ordering::Relaxed) > 1 {
+            let _ = write!(stderr, "\n\x1b[2K\x1b[1A");
+        }
+        let _ = stderr.flush();
+    }
+}
+
+fn fraction(done: u64, total: u64) -> f64 {
+    (done as f64 / total as f64).clamp(0.0, 1.0)
+}
+
+fn bar(fraction: f64) -> String {
+    let filled = ((fraction * WIDTH as f64) as usize).min(WIDTH);
+    let mut bar = "=".repeat(filled);
+    if filled < WIDTH {
+        bar.push('>');
+        bar.push_str(&" ".repeat(WIDTH - filled - 1));
+    }
+    format!("[{bar}]")
+}
+
+fn human_bytes(bytes: f64) -> String {
+    const UNITS: [&str; 4] = ["B", "KiB", "MiB", "GiB"];
+    let mut value = bytes;
+    let mut unit = 0;
+    while value >= 1024.0 && unit < UNITS.len() - 1 {
+        value /= 1024.0;
+        unit += 1;
+    }
+
+    if unit == 0 {
+        format!("{value:.0} {}", UNITS[unit])
+    } else {
+        format!("{value:.1} {}", UNITS[unit])
+    }
+}
+
+fn human_duration(secs: f64) -> String {
+    let secs = secs.ceil() as u64;
+    match secs {
+        0..=59 => format!("{secs}s"),
+        60..=3599 => format!("{}m{:02}s", secs / 60, secs % 60),
+        _ => format!("{}h{:02}m", secs / 3600, secs % 3600 / 60),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{bar, human_bytes, human_duration};
+
+    #[test]
+    fn test_render() {
+        assert_eq!(bar(0.0), format!("[>{}]", " ".repeat(23)));
+        assert_eq!(bar(0.5), format!("[{}>{}]", "=".repeat(12), " ".repeat(11)));
+        assert_eq!(bar(1.0), format!("[{}]", "=".repeat(24)));
+
+        assert_eq!(human_bytes(512.0), "512 B");
+        assert_eq!(human_bytes(1536.0), "1.5 KiB");
+        assert_eq!(human_bytes(10.0 * 1024.0 * 1024.0), "10.0 MiB");
+
+        assert_eq!(human_duration(5.2), "6s");
+        assert_eq!(human_duration(125.0), "2m05s");
+        assert_eq!(human_duration(3700.0), "1h01m");
+    }
+}
@@ -0,0 +1,545 @@
+//! Offline verification of sigstore bundles, against a pinned identity, issuer, and trust root.
+//! Only the signed entry timestamp of the transparency log entry is checked,
+//! since inclusion proofs and certificate transparency need the network or more trust roots.
+
+use std::fs;
+
+use anyhow::{bail, ensure, Context};
+use base64::{engine::general_purpose::STANDARD, Engine};
+use p256::ecdsa::signature::hazmat::PrehashVerifier;
+use serde::Deserialize;
+use x509_cert::{
+    der::{
+        asn1::{ObjectIdentifier, Utf8StringRef},
+        Decode, Encode,
+    },
+    ext::pkix::{name::GeneralName, ExtendedKeyUsage, SubjectAltName},
+    spki::SubjectPublicKeyInfoOwned,
+    Certificate,
+};
+
+use crate::{
+    config::SigstorePolicy,
+    hashing::{sha256, Hasher},
+};
+
+const ECDSA_WITH_SHA256: ObjectIdentifier = ObjectIdentifier::new_unwrap("1.2.840.10045.4.3.2");
+const ECDSA_WITH_SHA384: ObjectIdentifier = ObjectIdentifier::new_unwrap("1.2.840.10045.4.3.3");
+const SECP256R1: ObjectIdentifier = ObjectIdentifier::new_unwrap("1.2.840.10045.3.1.7");
+const SECP384R1: ObjectIdentifier = ObjectIdentifier::new_unwrap("1.3.132.0.34");
+const SUBJECT_ALT_NAME: ObjectIdentifier = ObjectIdentifier::new_unwrap("2.5.29.17");
+const EXTENDED_KEY_USAGE: ObjectIdentifier = ObjectIdentifier::new_unwrap("2.5.29.37");
+const CODE_SIGNING: ObjectIdentifier = ObjectIdentifier::new_unwrap("1.3.6.1.5.5.7.3.3");
+/// Fulcio OIDC issuer, as a DER encoded `UTF8String`.
+const FULCIO_ISSUER_V2: ObjectIdentifier = ObjectIdentifier::new_unwrap("1.3.6.1.4.1.57264.1.8");
+/// Fulcio OIDC issuer, as raw bytes. (Deprecated)
+const FULCIO_ISSUER: ObjectIdentifier = ObjectIdentifier::new_unwrap("1.3.6.1.4.1.57264.1.1");
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Bundle {
+    verification_material: VerificationMaterial,
+    message_signature: MessageSignature,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct VerificationMaterial {
+    certificate: Option<RawBytes>,                // Bundle v0.3
+    x509_certificate_chain: Option<Certificates>, // Bundle v0.1 and v0.2
+    tlog_entries: Vec<TlogEntry>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct TlogEntry {
+    log_index: String,
+    log_id: LogId,
+    integrated_time: String,
+    inclusion_promise: Option<InclusionPromise>,
+    canonicalized_body: String,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct InclusionPromise {
+    signed_entry_timestamp: String,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct LogId {
+    key_id: String,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct MessageSignature {
+    message_digest: Option<MessageDigest>,
+    signature: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct MessageDigest {
+    algorithm: String,
+    digest: String,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct RawBytes {
+    raw_bytes: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct Certificates {
+    certificates: Vec<RawBytes>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct TrustedRoot {
+    tlogs: Vec<TransparencyLog>,
+    certificate_authorities: Vec<CertificateAuthority>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct TransparencyLog {
+    public_key: TlogPublicKey,
+    log_id: LogId,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct TlogPublicKey {
+    raw_bytes: String,
+    valid_for: Option<ValidFor>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct CertificateAuthority {
+    cert_chain: Certificates,
+    valid_for: Option<ValidFor>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ValidFor {
+    start: Option<String>,
+    end: Option<String>,
+}
+impl ValidFor {
+    fn contains(&self, time: u64) -> anyhow::Result<bool> {
+        let start = self.start.as_deref().map(rfc3339_to_unix).transpose()?;
+        let end = self.end.as_deref().map(rfc3339_to_unix).transpose()?;
+        Ok(start.map_or(true, |s| s <= time) && end.map_or(true, |e| time <= e))
+    }
+}
+
+/// hashedrekord transparency log entry.
+#[derive(Debug, Deserialize)]
+struct RekorBody {
+    kind: String,
+    spec: RekorSpec,
+}
+
+#[derive(Debug, Deserialize)]
+struct RekorSpec {
+    data: RekorData,
+    signature: RekorSignature,
+}
+
+#[derive(Debug, Deserialize)]
+struct RekorData {
+    hash: RekorHash,
+}
+
+#[derive(Debug, Deserialize)]
+struct RekorHash {
+    algorithm: String,
+    value: String,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct RekorSignature {
+    content: String,
+    public_key: RekorPublicKey,
+}
+
+#[derive(Debug, Deserialize)]
+struct RekorPublicKey {
+    content: String,
+}
+
+pub struct Sigstore {
+    identity: String,
+    issuer: String,
+    trust_root: TrustedRoot,
+}
+impl Sigstore {
+    pub fn new(policy: &SigstorePolicy) -> Self {
+        let trust_root = fs::read_to_string(&policy.trust_root).unwrap_or_else(|_| {
+            panic!(
+                "Could not read sigstore trust root at '{}'.",
+                policy.trust_root.display()
+            )
+        });
+
+        Self::from_str(&policy.identity, &policy.issuer, &trust_root)
+    }
+
+    fn from_str(identity: &str, issuer: &str, trust_root: &str) -> Self {
+        Self {
+            identity: identity.to_string(),
+            issuer: issuer.to_string(),
+            trust_root: serde_json::from_str(trust_root)
+                .expect("Sigstore trust root is malformed."),
+        }
+    }
+
+    /// Verifies a bundle against the sha256 `digest` of the signed file.
+    pub fn verify(&self, bundle: &str, digest: &[u8]) -> anyhow::Result<()> {
+        let bundle: Bundle = serde_json::from_str(bundle).context("Bundle is malformed")?;
+        let material = &bundle.verification_material;
+
+        let leaf_der = match (&material.certificate, &material.x509_certificate_chain) {
+            (Some(cert), _) => decode(&cert.raw_bytes)?,
+            (None, Some(chain)) => decode(
+                &chain
+                    .certificates
+                    .first()
+                    .context("Bundle has an empty certificate chain")?
+                    .raw_bytes,
+            )?,
+            (None, None) => bail!("Bundle has no certificate"),
+        };
+        let leaf = Certificate::from_der(&leaf_der).context("Certificate is malformed")?;
+        let signature = decode(&bundle.message_signature.signature)?;
+
+        if let Some(message_digest) = &bundle.message_signature.message_digest {
+            ensure!(
+                message_digest.algorithm.eq("SHA2_256")
+                    && decode(&message_digest.digest)?.eq(digest),
+                "Bundle digest does not match the file"
+            );
+        }
+
+        let integrated_time =
+            self.verify_tlog(&material.tlog_entries, &leaf_der, &signature, digest)?;
+        self.verify_certificate(&leaf, integrated_time)?;
+
+        // The file itself
+        ensure!(
+            verify_prehash(
+                &leaf.tbs_certificate.subject_public_key_info,
+                digest,
+                &signature
+            )?,
+            "Signature does not match the file"
+        );
+
+        Ok(())
+    }
+
+    /// Checks the signed entry timestamp and that the entry is for this signature,
+    /// returning when it was added to the log.
+    fn verify_tlog(
+        &self,
+        entries: &[TlogEntry],
+        leaf_der: &[u8],
+        signature: &[u8],
+        digest: &[u8],
+    ) -> anyhow::Result<u64> {
+        let entry = entries
+            .iter()
+            .find(|e| e.inclusion_promise.is_some())
+            .context("Bundle has no transparency log entry with a signed entry timestamp")?;
+        let integrated_time: u64 = entry
+            .integrated_time
+            .parse()
+            .context("Integrated time is malformed")?;
+        let log_index: u64 = entry.log_index.parse().context("Log index is malformed")?;
+
+        let log = self
+            .trust_root
+            .tlogs
+            .iter()
+            .find(|l| l.log_id.key_id.eq(&entry.log_id.key_id))
+            .context("Transparency log is not in the trust root")?;
+        if let Some(valid_for) = &log.public_key.valid_for {
+            ensure!(
+                valid_for.contains(integrated_time)?,
+                "Transparency log key was not valid when the entry was added"
+            );
+        }
+
+        // Canonical JSON, with sorted keys
+        let payload = format!(
+            r#"{{"body":"{}","integratedTime":{integrated_time},"logID":"{}","logIndex":{log_index}}}"#,
+            entry.canonicalized_body,
+            const_hex::encode(decode(&entry.log_id.key_id)?)
+        );
+        let log_key = SubjectPublicKeyInfoOwned::from_der(&decode(&log.public_key.raw_bytes)?)
+            .context("Transparency log key is malformed")?;
+        let set = decode(
+            &entry
+                .inclusion_promise
+                .as_ref()
+                .expect("Checked above")
+                .signed_entry_timestamp,
+        )?;
+        ensure!(
+            verify_prehash(&log_key, &sha256(payload.as_bytes()), &set)?,
+            "Signed entry timestamp does not match"
+        );
+
+        let body: RekorBody = serde_json::from_slice(&decode(&entry.canonicalized_body)?)
+            .context("Transparency log entry is malformed")?;
+        ensure!(
+            body.kind.eq("hashedrekord"),
+            "Transparency log entry is not a hashedrekord"
+        );
+        ensure!(
+            body.spec.data.hash.algorithm.eq("sha256")
+                && body.spec.data.hash.value.eq(&const_hex::encode(digest)),
+            "Transparency log entry is for another file"
+        );
+        ensure!(
+            decode(&body.spec.signature.content)? == signature,
+            "Transparency log entry is for another signature"
+        );
+        ensure!(
+            pem_to_der(&decode(&body.spec.signature.public_key.content)?)? == leaf_der,
+            "Transparency log entry is for another certificate"
+        );
+
+        Ok(integrated_time)
+    }
+
+    /// Checks the certificate was issued by the trust root, was valid when the entry was
+    /// added to the log, and is for the pinned identity and issuer.
+    fn verify_certificate(&self, leaf: &Certificate, time: u64) -> anyhow::Result<()> {
+        let tbs = &leaf.tbs_certificate;
+        let validity = &tbs.validity;
+        ensure!(
+            validity.not_before.to_unix_duration().as_secs() <= time
+                && time <= validity.not_after.to_unix_duration().as_secs(),
+            "Certificate was not valid when the entry was added"
+        );
+
+        let tbs_der = tbs.to_der()?;
+        let tbs_digest = match leaf.signature_algorithm.oid {
+            ECDSA_WITH_SHA256 => sha256(&tbs_der),
+            ECDSA_WITH_SHA384 => {
+                let mut hasher: Hasher = Box::<sha2::Sha384>::default();
+                hasher.update(&tbs_der);
+                hasher.finalize().into_vec()
+            }
+            oid => bail!("Certificate signature algorithm {oid} is not supported"),
+        };
+
+        let mut trusted = false;
+        for ca in &self.trust_root.certificate_authorities {
+            if let Some(valid_for) = &ca.valid_for {
+                if !valid_for.contains(time)? {
+                    continue;
+                }
+            }
+
+            for cert in &ca.cert_chain.certificates {
+                let cert = Certificate::from_der(&decode(&cert.raw_bytes)?)
+                    .context("Trust root certificate is malformed")?;
+                if cert.tbs_certificate.subject == tbs.issuer
+                    && verify_prehash(
+                        &cert.tbs_certificate.subject_public_key_info,
+                        &tbs_digest,
+                        leaf.signature.raw_bytes(),
+                    )?
+                {
+                    trusted = true;
+                }
+            }
+        }
+        ensure!(trusted, "Certificate was not issued by the trust root");
+
+        let extensions = tbs.extensions.as_deref().unwrap_or_default();
+
+        let code_signing = extensions
+            .iter()
+            .filter(|e| e.extn_id == EXTENDED_KEY_USAGE)
+            .map(|e| ExtendedKeyUsage::from_der(e.extn_value.as_bytes()))
+            .collect::<Result<Vec<_>, _>>()
+            .context("Certificate extended key usage is malformed")?
+            .iter()
+            .any(|eku| eku.0.contains(&CODE_SIGNING));
+        ensure!(code_signing, "Certificate is not for code signing");
+
+        let identities: Vec<String> = extensions
+            .iter()
+            .filter(|e| e.extn_id == SUBJECT_ALT_NAME)
+            .map(|e| SubjectAltName::from_der(e.extn_value.as_bytes()))
+            .collect::<Result<Vec<_>, _>>()
+            .context("Certificate subject alternative name is malformed")?
+            .into_iter()
+            .flat_map(|san| san.0)
+            .filter_map(|name| match name {
+                GeneralName::Rfc822Name(s) | GeneralName::UniformResourceIdentifier(s) => {
+                    Some(s.to_string())
+                }
+                _ => None,
+            })
+            .collect();
+        ensure!(
+            identities.contains(&self.identity),
+            "Certificate identity {identities:?} is not {}",
+            self.identity
+        );
+
+        let issuer = if let Some(e) = extensions.iter().find(|e| e.extn_id == FULCIO_ISSUER_V2) {
+            Utf8StringRef::from_der(e.extn_value.as_bytes())
+                .context("Certificate issuer is malformed")?
+                .as_str()
+                .to_string()
+        } else if let Some(e) = extensions.iter().find(|e| e.extn_id == FULCIO_ISSUER) {
+            String::from_utf8(e.extn_value.as_bytes().to_vec())
+                .context("Certificate issuer is malformed")?
+        } else {
+            bail!("Certificate has no issuer")
+        };
+        ensure!(
+            issuer.eq(&self.issuer),
+            "Certificate issuer {issuer} is not {}",
+            self.issuer
+        );
+
+        Ok(())
+    }
+}
+
+/// Verifies an ECDSA P-256 or P-384 signature over a digest.
+fn verify_prehash(
+    key: &SubjectPublicKeyInfoOwned,
+    digest: &[u8],
+    signature: &[u8],
+) -> anyhow::Result<bool> {
+    let curve: ObjectIdentifier = key
+        .algorithm
+        .parameters
+        .as_ref()
+        .context("Public key has no curve")?
+        .decode_as()
+        .context("Public key curve is malformed")?;
+    let key = key.subject_public_key.raw_bytes();
+
+    Ok(match curve {
+        SECP256R1 => {
+            let key = p256::ecdsa::VerifyingKey::from_sec1_bytes(key)?;
+            p256::ecdsa::Signature::from_der(signature)
+                .is_ok_and(|s| key.verify_prehash(digest, &s).is_ok())
+        }
+        SECP384R1 => {
+            let key = p384::ecdsa::VerifyingKey::from_sec1_bytes(key)?;
+            p384::ecdsa::Signature::from_der(signature)
+                .is_ok_and(|s| key.verify_prehash(digest, &s).is_ok())
+        }
+        curve => bail!("Public key curve {curve} is not supported"),
+    })
+}
+
+fn decode(s: &str) -> anyhow::Result<Vec<u8>> {
+    STANDARD.decode(s).context("Base64 is malformed")
+}
+
+fn pem_to_der(pem: &[u8]) -> anyhow::Result<Vec<u8>> {
+    let pem = core::str::from_utf8(pem).context("PEM is malformed")?;
+    let b64: String = pem
+        .lines()
+        .map(str::trim)
+        .filter(|l| !l.starts_with("-----"))
+        .collect();
+    decode(&b64)
+}
+
+/// Parses `YYYY-MM-DDTHH:MM:SS[.fraction]Z` to seconds since the unix epoch.
+fn rfc3339_to_unix(s: &str) -> anyhow::Result<u64> {
+    let malformed = || format!("Time {s} is malformed");
+    let field = |range: core::ops::Range<usize>| -> anyhow::Result<u64> {
+        s.get(range)
+            .and_then(|f| f.parse().ok())
+            .with_context(malformed)
+    };
+    ensure!(
+        s.len() >= 20 && s.ends_with('Z') && s.as_bytes()[10] == b'T',
+        malformed()
+    );
+
+    let (year, month, day) = (field(0..4)?, field(5..7)?, field(8..10)?);
+    let (hour, minute, second) = (field(11..13)?, field(14..16)?, field(17..19)?);
+    ensure!(
+        (1..=12).contains(&month)
+            && (1..=31).contains(&day)
+            && hour < 24
+            && minute < 60
+            && second <= 60,
+        malformed()
+    );
+
+    // https://howardhinnant.github.io/date_algorithms.html#days_from_civil
+    let year = if month <= 2 {
+        year.checked_sub(1).with_context(malformed)?
+    } else {
+        year
+    };
+    let era = year / 400;
+    let yoe = year - era * 400;
+    let doy = (153 * ((month + 9) % 12) + 2) / 5 + day - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    let days = (era * 146_097 + doe)
+        .checked_sub(719_468)
+        .with_context(malformed)?;
+
+    Ok(days * 86_400 + hour * 3600 + minute * 60 + second)
+}
+
+#[cfg(test)]
+mod test {
+    use super::{rfc3339_to_unix, sha256, Sigstore};
+
+    const ISSUER: &str = "https://issuer.example.com";
+
+    fn policy(identity: &str, issuer: &str) -> Sigstore {
+        let trust_root = include_str!("../test/sigstore/trusted_root.json");
+        Sigstore::from_str(identity, issuer, trust_root)
+    }
+
+    #[test]
+    fn test_verify() {
+        let artifact = include_bytes!("../test/sigstore/artifact.txt");
+        let bundle = include_str!("../test/sigstore/artifact.txt.sigstore.json");
+
+        let sigstore = policy("dev@example.com", ISSUER);
+        let digest = &sha256(artifact);
+        sigstore.verify(bundle, digest).unwrap();
+        assert!(sigstore.verify(bundle, &sha256(b"tampered")).is_err());
+
+        let sigstore = policy("other@example.com", ISSUER);
+        assert!(sigstore.verify(bundle, digest).is_err());
+
+        let sigstore = policy("dev@example.com", "https://other.example.com");
+        assert!(sigstore.verify(bundle, digest).is_err());
+    }
+
+    #[test]
+    fn test_rfc3339_to_unix() {
+        assert_eq!(rfc3339_to_unix("1970-01-01T00:00:00Z").unwrap(), 0);
+        assert_eq!(
+            rfc3339_to_unix("2025-10-09T08:53:20.000Z").unwrap(),
+            1_760_000_000
+        );
+        assert!(rfc3339_to_unix("2025-10-09 08:53:20").is_err());
+        assert!(rfc3339_to_unix("0000-01-01T00:00:00Z").is_err());
+        assert!(rfc3339_to_unix("2025-10-00T00:00:00Z").is_err());
+    }
+}
@@ -0,0 +1,264 @@
+use std::{
+    env::consts,
+    path::Path,
+    process::{Command, Output},
+};
+
+use crate::{status, BUILD_TARGET};
+
+/// The oldest glibc that rust's gnu linux targets support.
+const MIN_GLIBC: (u32, u32) = (2, 17);
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Libc {
+    Gnu(Option<(u32, u32)>),
+    Musl,
+}
+
+/// Programs are run by absolute path, so nothing earlier in PATH is ever run instead.
+const UNAME: &[&str] = &["/usr/bin/uname", "/bin/uname"];
+const SYSCTL: &[&str] = &["/usr/sbin/sysctl", "/sbin/sysctl"];
+const GETCONF: &[&str] = &["/usr/bin/getconf", "/bin/getconf"];
+const LDD: &[&str] = &["/usr/bin/ldd", "/bin/ldd"];
+
+/// Picks the best target for the running host, explaining why.
+/// Falls back to the target cargo-prebuilt was built for when the host is not recognized.
+pub fn detect() -> String {
+    let (target, reason) = match consts::OS {
+        "linux" => detect_linux(),
+        "macos" => detect_macos(),
+        _ => None,
+    }
+    .unwrap_or_else(|| {
+        (
+            BUILD_TARGET.to_string(),
+            "same as cargo-prebuilt".to_string(),
+        )
+    });
+
+    status!(bright_blue, "Detected", "target {target} ({reason}).");
+    target
+}
+
+/// The cpu of the host from `uname -m`, or the one cargo-prebuilt was built for.
+fn machine() -> String {
+    command(UNAME, &["-m"]).unwrap_or_else(|| consts::ARCH.to_string())
+}
+
+fn detect_linux() -> Option<(String, String)> {
+    let arch = linux_arch(&machine())?;
+    let libc = detect_libc();
+    let target = linux_target(arch, libc);
+    let reason = match libc {
+        Some(Libc::Gnu(Some((major, minor)))) if (major, minor) >= MIN_GLIBC => {
+            format!("{arch} cpu, glibc {major}.{minor}")
+        }
+        Some(Libc::Gnu(Some((major, minor)))) => format!(
+            "{arch} cpu, glibc {major}.{minor} is older than {}.{}",
+            MIN_GLIBC.0, MIN_GLIBC.1
+        ),
+        Some(Libc::Gnu(None)) => format!("{arch} cpu, glibc of unknown version"),
+        Some(Libc::Musl) => format!("{arch} cpu, musl libc"),
+        None => format!("{arch} cpu, no glibc found"),
+    };
+
+    Some((target, reason))
+}
+
+fn detect_macos() -> Option<(String, String)> {
+    // x86_64 binaries running under rosetta see an x86_64 machine
+    let translated = command(SYSCTL, &["-n", "sysctl.proc_translated"]).is_some_and(|s| s == "1");
+
+    match (machine().as_str(), translated) {
+        ("arm64" | "aarch64", _) | ("x86_64", true) => Some((
+            "aarch64-apple-darwin".to_string(),
+            format!(
+                "apple silicon{}",
+                if translated { ", under rosetta" } else { "" }
+            ),
+        )),
+        ("x86_64", false) => Some(("x86_64-apple-darwin".to_string(), "intel cpu".to_string())),
+        _ => None,
+    }
+}
+
+/// Checks glibc first with getconf, then asks ldd, which prints which libc it is for.
+fn detect_libc() -> Option<Libc> {
+    if let Some(version) = command(GETCONF, &["GNU_LIBC_VERSION"]) {
+        return Some(Libc::Gnu(parse_glibc_version(&version)));
+    }
+
+    // musl's ldd prints its version to stderr and exits with an error
+    let ldd = run(LDD, &["--version"])?;
+    let out = format!(
+        "{}{}",
+        String::from_utf8_lossy(&ldd.stdout),
+        String::from_utf8_lossy(&ldd.stderr)
+    );
+    parse_ldd(&out)
+}
+
+fn parse_ldd(out: &str) -> Option<Libc> {
+    let first = out.lines().next()?;
+    let lower = first.to_lowercase();
+    if lower.contains("musl") {
+        Some(Libc::Musl)
+    } else if lower.contains("glibc") || lower.contains("gnu libc") {
+        Some(Libc::Gnu(parse_glibc_version(first)))
+    } else {
+        None
+    }
+}
+
+/// Parses the last word of a line like `glibc 2.39` or `ldd (GNU libc) 2.39` as a version.
+fn parse_glibc_version(s: &str) -> Option<(u32, u32)> {
+    let version = s.split_whitespace().last()?;
+    let mut parts = version.split('.');
+    let major = parts.next()?.parse().ok()?;
+    let minor = parts.next()?.parse().ok()?;
+    Some((major, minor))
+}
+
+/// Converts `uname -m`, or `std::env::consts::ARCH`, to a rust target arch.
+fn linux_arch(machine: &str) -> Option<&'static str> {
+    Some(match machine {
+        "x86_64" | "amd64" => "x86_64",
+        "aarch64" | "arm64" => "aarch64",
+        "i386" | "i486" | "i586" | "i686" | "x86" => "i686",
+        "armv7l" | "armv8l" | "arm" => "armv7",
+        "riscv64" => "riscv64gc",
+        "ppc64le" => "powerpc64le",
+        "powerpc64" if cfg!(target_endian = "little") => "powerpc64le",
+        "s390x" => "s390x",
+        _ => return None,
+    })
+}
+
+fn linux_target(arch: &str, libc: Option<Libc>) -> String {
+    let gnu = matches!(libc, Some(Libc::Gnu(v)) if v.map_or(true, |v| v >= MIN_GLIBC));
+    let env = match (gnu, arch) {
+        (true, "armv7") => "gnueabihf",
+        (false, "armv7") => "musleabihf",
+        (true, _) => "gnu",
+        (false, _) => "musl",
+    };
+    format!("{arch}-unknown-linux-{env}")
+}
+
+/// The closest of `available` to a mistyped target, if any are close enough.
+pub fn suggest<'a>(target: &str, available: &[&'a str]) -> Option<&'a str> {
+    available
+        .iter()
+        .map(|a| (levenshtein(target, a), *a))
+        .filter(|(distance, _)| *distance <= target.len() / 2)
+        .min_by_key(|(distance, _)| *distance)
+        .map(|(_, a)| a)
+}
+
+fn levenshtein(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut row: Vec<usize> = (0..=b.len()).collect();
+
+    for (i, ca) in a.chars().enumerate() {
+        let mut prev = row[0];
+        row[0] = i + 1;
+        for (j, cb) in b.iter().enumerate() {
+            let cur = row[j + 1];
+            row[j + 1] = if ca == *cb {
+                prev
+            } else {
+                1 + prev.min(row[j]).min(cur)
+            };
+            prev = cur;
+        }
+    }
+
+    row[b.len()]
+}
+
+/// Runs the first of `paths` that exists.
+fn run(paths: &[&str], args: &[&str]) -> Option<Output> {
+    let program = paths.iter().find(|p| Path::new(p).is_file())?;
+    Command::new(program).args(args).output().ok()
+}
+
+fn command(paths: &[&str], args: &[&str]) -> Option<String> {
+    let out = run(paths, args)?;
+    out.status
+        .success()
+        .then(|| String::from_utf8_lossy(&out.stdout).trim().to_string())
+        .filter(|s| !s.is_empty())
+}
+
+#[cfg(test)]
+mod test {
+    use super::{
+        levenshtein, linux_arch, linux_target, parse_glibc_version, parse_ldd, suggest, Libc,
+    };
+
+    #[test]
+    fn test_parse_glibc_version() {
+        assert_eq!(parse_glibc_version("glibc 2.39"), Some((2, 39)));
+        assert_eq!(
+            parse_glibc_version("ldd (Ubuntu GLIBC 2.35-0ubuntu3.8) 2.35"),
+            Some((2, 35))
+        );
+        assert_eq!(parse_glibc_version("glibc"), None);
+    }
+
+    #[test]
+    fn test_parse_ldd() {
+        assert_eq!(
+            parse_ldd("musl libc (x86_64)\nVersion 1.2.5\n"),
+            Some(Libc::Musl)
+        );
+        assert_eq!(
+            parse_ldd("ldd (GNU libc) 2.39\nCopyright (C) 2024"),
+            Some(Libc::Gnu(Some((2, 39))))
+        );
+        assert_eq!(parse_ldd("ldd: unknown option"), None);
+    }
+
+    #[test]
+    fn test_linux_target() {
+        let glibc = Some(Libc::Gnu(Some((2, 39))));
+        assert_eq!(linux_target("x86_64", glibc), "x86_64-unknown-linux-gnu");
+        assert_eq!(
+            linux_target("armv7", glibc),
+            "armv7-unknown-linux-gnueabihf"
+        );
+        assert_eq!(
+            linux_target("aarch64", Some(Libc::Gnu(Some((2, 12))))),
+            "aarch64-unknown-linux-musl"
+        );
+        assert_eq!(
+            linux_target("x86_64", Some(Libc::Musl)),
+            "x86_64-unknown-linux-musl"
+        );
+        assert_eq!(linux_target("x86_64", None), "x86_64-unknown-linux-musl");
+        assert_eq!(linux_arch("x86"), Some("i686"));
+        assert_eq!(linux_arch("ppc64le"), Some("powerpc64le"));
+        assert_eq!(linux_arch("mips"), None);
+    }
+
+    #[test]
+    fn test_suggest() {
+        assert_eq!(levenshtein("kitten", "sitting"), 3);
+        assert_eq!(levenshtein("", "abc"), 3);
+
+        let available = [
+            "aarch64-unknown-linux-gnu",
+            "x86_64-unknown-linux-gnu",
+            "x86_64-unknown-linux-musl",
+        ];
+        assert_eq!(
+            suggest("x86_64-linux-gnu", &available),
+            Some("x86_64-unknown-linux-gnu")
+        );
+        assert_eq!(
+            suggest("x86_64-unknown-linux-musle", &available),
+            Some("x86_64-unknown-linux-musl")
+        );
+        assert_eq!(suggest("wasm32-wasip1", &available), None);
+    }
+}